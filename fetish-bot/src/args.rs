@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use fetish_common::models::chat_policy::Policy;
 
 #[derive(Parser, Debug)]
 pub struct Args {
@@ -8,4 +9,31 @@ pub struct Args {
     pub tg_database_directory: String,
     #[arg(short, long, default_value = "db.sqlite")]
     pub database_path: PathBuf,
+    /// Record the sanctions in the database without sending them
+    #[arg(long)]
+    pub dry_run: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Maintenance commands, the bot runs when none is given
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Add usernames to the known scam handles
+    AddHandles { handles: Vec<String> },
+    /// Deactivate known scam handles
+    RemoveHandles { handles: Vec<String> },
+    /// Set what the bot does in a chat: observe, warn, report or ignore
+    SetChatPolicy {
+        #[arg(allow_negative_numbers = true)]
+        chat_id: i64,
+        policy: Policy,
+    },
+    /// List the chats with a policy
+    ChatPolicies,
+    /// List the changes of our status in chats, kicks and bans included
+    Memberships {
+        #[arg(allow_negative_numbers = true)]
+        chat_id: Option<i64>,
+    },
 }
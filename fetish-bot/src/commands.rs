@@ -0,0 +1,74 @@
+use chrono::Utc;
+use fetish_common::{
+    database::Database,
+    error::FetishResult,
+    models::{
+        chat_policy::{ChatPolicy, Policy},
+        membership_change::MembershipChange,
+        scam_handle::ScamHandle,
+    },
+};
+use log::info;
+
+use crate::args::Command;
+
+pub fn run(command: Command, db: &Database) -> FetishResult<()> {
+    match command {
+        Command::AddHandles { handles } => set_handles_active(db, handles, true),
+        Command::RemoveHandles { handles } => set_handles_active(db, handles, false),
+        Command::SetChatPolicy { chat_id, policy } => set_chat_policy(db, chat_id, policy),
+        Command::ChatPolicies => list_chat_policies(db),
+        Command::Memberships { chat_id } => list_membership_changes(db, chat_id),
+    }
+}
+
+fn set_handles_active(db: &Database, handles: Vec<String>, is_active: bool) -> FetishResult<()> {
+    for handle in handles {
+        let username = ScamHandle::normalize(&handle);
+        let added_at = match db.load::<ScamHandle>(username.clone())? {
+            Some(handle) => handle.added_at,
+            None => Utc::now().timestamp(),
+        };
+        db.save(&ScamHandle {
+            username: username.clone(),
+            is_active,
+            added_at,
+        })?;
+        info!("Scam handle '@{username}' active: {is_active}");
+    }
+    Ok(())
+}
+
+fn set_chat_policy(db: &Database, chat_id: i64, policy: Policy) -> FetishResult<()> {
+    db.save(&ChatPolicy {
+        chat_id,
+        policy,
+        updated_at: Utc::now().timestamp(),
+    })?;
+    info!("Chat {chat_id} policy: {policy}");
+    Ok(())
+}
+
+fn list_chat_policies(db: &Database) -> FetishResult<()> {
+    for chat_policy in db.load_all::<ChatPolicy>()? {
+        println!("{}\t{}", chat_policy.chat_id, chat_policy.policy);
+    }
+    Ok(())
+}
+
+fn list_membership_changes(db: &Database, chat_id: Option<i64>) -> FetishResult<()> {
+    let changes = match chat_id {
+        Some(chat_id) => MembershipChange::select_by_chat_id(chat_id, db.connection())?,
+        None => db.load_all::<MembershipChange>()?,
+    };
+    for change in changes {
+        println!(
+            "{}\t{}\t{} -> {}",
+            change.chat_id,
+            change.changed_at,
+            MembershipChange::status_name(&change.old_status),
+            MembershipChange::status_name(&change.new_status)
+        );
+    }
+    Ok(())
+}
@@ -1,24 +1,60 @@
+use std::sync::Arc;
+
 use clap::Parser;
 use fetish_common::{
     application::Application,
+    database::Database,
+    detectors::{
+        forward_detector::ForwardDetector, handle_detector::HandleDetector,
+        image_detector::ImageDetector, keyword_detector::KeywordDetector,
+        link_detector::LinkDetector, profile_detector::ProfileDetector,
+        scammer_account_detector::ScammerAccountDetector,
+        telegram_flag_detector::TelegramFlagDetector, DetectorPipeline,
+    },
     error::FetishResult,
     location::Location,
+    media::MediaConfig,
+    rules::RulesStore,
     states::{
-        closing_state::ClosingState, exploitation_state::ExploitationState, login_state::LoginState,
+        closing_state::ClosingState, exploitation_state::ExploitationState,
+        login_state::LoginState, reference_images_state::ReferenceImagesState,
     },
 };
 
 mod args;
+mod commands;
 
 #[tokio::main]
 async fn main() -> FetishResult<()> {
     env_logger::init();
     let args = args::Args::parse();
+    if let Some(command) = args.command {
+        return commands::run(command, &Database::new(&args.database_path)?);
+    }
+    let rules = Arc::new(RulesStore::load("res")?);
+    let media = MediaConfig::load("res/media.json")?;
     Application::new()
+        .set_download_policy(media.downloads.clone())
+        .set_profile_detector(ProfileDetector::new(rules.clone()))
         .add_state(LoginState::new(&args.tg_database_directory))
-        .add_state(ExploitationState::new(
-            Location::new(48.864716, 2.349014).compute_locations(860., 5),
-        ))
+        .add_state(ReferenceImagesState::new("res/reference_images"))
+        .add_state(
+            ExploitationState::new(
+                Location::new(48.864716, 2.349014).compute_locations(860., 5),
+                DetectorPipeline::new()
+                    .add_detector(ScammerAccountDetector)
+                    .add_detector(TelegramFlagDetector)
+                    .add_detector(KeywordDetector::new(rules.clone()))
+                    .add_detector(LinkDetector::new(rules.clone()))
+                    .add_detector(HandleDetector)
+                    .add_detector(ForwardDetector::new(rules.clone()))
+                    .add_detector(ImageDetector::new(media.downloads))
+                    .add_detector(ProfileDetector::new(rules.clone())),
+                rules,
+                media.cache,
+            )
+            .set_dry_run(args.dry_run),
+        )
         .add_state(ClosingState)
         .run(&args.database_path)
         .await
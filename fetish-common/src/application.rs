@@ -11,8 +11,8 @@ use tokio::{
 };
 
 use crate::{
-    database::Database, error::FetishResult, states::ApplicationState,
-    update_dispatcher::UpdateDispatcher,
+    database::Database, detectors::profile_detector::ProfileDetector, error::FetishResult,
+    media::DownloadPolicy, states::ApplicationState, update_dispatcher::UpdateDispatcher,
 };
 
 pub struct ApplicationData {
@@ -28,11 +28,28 @@ unsafe impl Sync for ApplicationData {}
 
 pub struct Application {
     states: Vec<Box<dyn ApplicationState>>,
+    profile_detector: Option<ProfileDetector>,
+    download_policy: DownloadPolicy,
 }
 
 impl Application {
     pub fn new() -> Self {
-        Self { states: Vec::new() }
+        Self {
+            states: Vec::new(),
+            profile_detector: None,
+            download_policy: DownloadPolicy::default(),
+        }
+    }
+
+    /// Assesses users as the update dispatcher receives them
+    pub fn set_profile_detector(mut self, profile_detector: ProfileDetector) -> Self {
+        self.profile_detector = Some(profile_detector);
+        self
+    }
+
+    pub fn set_download_policy(mut self, download_policy: DownloadPolicy) -> Self {
+        self.download_policy = download_policy;
+        self
     }
 
     pub fn add_state<AppState: ApplicationState + 'static>(mut self, state: AppState) -> Self {
@@ -40,7 +57,7 @@ impl Application {
         self
     }
 
-    pub async fn run(self, db_path: &Path) -> FetishResult<()> {
+    pub async fn run(mut self, db_path: &Path) -> FetishResult<()> {
         info!("Fetish started");
 
         let client_id = tdlib::create_client();
@@ -61,6 +78,8 @@ impl Application {
                 auth_tx,
                 message_tx,
                 db.clone(),
+                self.profile_detector.take(),
+                self.download_policy.clone(),
             )?
             .run(),
         );
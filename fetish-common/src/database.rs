@@ -35,13 +35,18 @@ impl Database {
         Ok(())
     }
 
-    pub fn load<DatabaseEntity: AutoRequestable<UniqueIdentifier = i64>>(
+    pub fn load<DatabaseEntity: AutoRequestable>(
         &self,
-        id: i64,
+        id: DatabaseEntity::UniqueIdentifier,
     ) -> FetishResult<Option<DatabaseEntity>> {
         DatabaseEntity::select_by_id(id, &self.conn)
     }
 
+    /// For the queries specific to a model
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+
     pub fn load_all<DatabaseEntity: AutoRequestable>(&self) -> FetishResult<Vec<DatabaseEntity>> {
         DatabaseEntity::select_all(&self.conn)
     }
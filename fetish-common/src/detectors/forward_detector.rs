@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use tdlib::{
+    enums::{MessageForwardOrigin, MessageSender},
+    types::{
+        Message, MessageForwardInfo, MessageForwardOriginChannel, MessageForwardOriginChat,
+        MessageForwardOriginUser, MessageSenderChat, MessageSenderUser,
+    },
+};
+
+use crate::{
+    error::FetishResult,
+    models::{forward_source::ForwardSource, scammer::Scammer},
+    rules::RulesStore,
+};
+
+use super::{
+    telegram_flag_detector::telegram_flag, DetectionContext, ScamDetector, Verdict, VerdictKind,
+};
+
+const FORWARD_WEIGHT: f64 = 1.;
+
+/// Flags messages forwarded from a known scammer, from an account flagged by Telegram or from a
+/// chat that already fed enough scam forwards
+pub struct ForwardDetector {
+    rules: Arc<RulesStore>,
+}
+
+impl ForwardDetector {
+    pub fn new(rules: Arc<RulesStore>) -> Self {
+        Self { rules }
+    }
+}
+
+#[async_trait]
+impl ScamDetector for ForwardDetector {
+    fn name(&self) -> &'static str {
+        "forward"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let Some(origin) = forward_origin(context.message) else {
+            return Ok(None);
+        };
+        let scam_forwards = self.rules.get().scoring.forward_sources.scam_forwards;
+        let mut reasons = Vec::new();
+        {
+            let db = context.db.lock().unwrap();
+            let source = match origin {
+                MessageSender::User(MessageSenderUser { user_id }) => {
+                    if db.load::<Scammer>(user_id)?.is_some() {
+                        reasons.push(format!("forwarded from scammer {user_id}"));
+                    }
+                    format!("user {user_id}")
+                }
+                MessageSender::Chat(MessageSenderChat { chat_id }) => {
+                    if let Some(forward_source) = db.load::<ForwardSource>(chat_id)? {
+                        if forward_source.scam_forwards >= scam_forwards {
+                            reasons.push(format!(
+                                "forwarded from chat {chat_id} ({} scam forwards)",
+                                forward_source.scam_forwards
+                            ));
+                        }
+                    }
+                    format!("chat {chat_id}")
+                }
+            };
+            if let Some(flag) = telegram_flag(&db, &origin)? {
+                reasons.push(format!(
+                    "forwarded from {source} flagged as {flag} by Telegram"
+                ));
+            }
+        }
+        if reasons.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Verdict::new(
+            VerdictKind::ScamMessage,
+            FORWARD_WEIGHT * reasons.len() as f64,
+            reasons.join(", "),
+        )))
+    }
+}
+
+/// Who the message was originally sent by, hidden users and imports can't be told apart
+pub fn forward_origin(message: &Message) -> Option<MessageSender> {
+    let MessageForwardInfo { origin, .. } = message.forward_info.as_ref()?;
+    match *origin {
+        MessageForwardOrigin::User(MessageForwardOriginUser { sender_user_id }) => {
+            Some(MessageSender::User(MessageSenderUser {
+                user_id: sender_user_id,
+            }))
+        }
+        MessageForwardOrigin::Chat(MessageForwardOriginChat { sender_chat_id, .. }) => {
+            Some(MessageSender::Chat(MessageSenderChat {
+                chat_id: sender_chat_id,
+            }))
+        }
+        MessageForwardOrigin::Channel(MessageForwardOriginChannel { chat_id, .. }) => {
+            Some(MessageSender::Chat(MessageSenderChat { chat_id }))
+        }
+        MessageForwardOrigin::HiddenUser(_) | MessageForwardOrigin::MessageImport(_) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{models::scammer::ScammerReason, test_utils};
+
+    use super::*;
+
+    fn forwarded(origin: MessageForwardOrigin) -> Message {
+        let mut message = test_utils::message(-100, 1, 42, test_utils::text("hello"));
+        message.forward_info = Some(MessageForwardInfo {
+            origin,
+            date: 0,
+            public_service_announcement_type: String::new(),
+            from_chat_id: 0,
+            from_message_id: 0,
+        });
+        message
+    }
+
+    fn from_user(sender_user_id: i64) -> Message {
+        forwarded(MessageForwardOrigin::User(MessageForwardOriginUser {
+            sender_user_id,
+        }))
+    }
+
+    fn from_channel(chat_id: i64) -> Message {
+        forwarded(MessageForwardOrigin::Channel(MessageForwardOriginChannel {
+            chat_id,
+            message_id: 1,
+            author_signature: String::new(),
+        }))
+    }
+
+    #[tokio::test]
+    async fn test_forward_from_listed_scammer() {
+        let db = test_utils::database();
+        db.lock()
+            .unwrap()
+            .save(&Scammer::new(7, ScammerReason::ManualFlag, "test", 0))
+            .unwrap();
+        let detector = ForwardDetector::new(test_utils::rules());
+
+        let message = from_user(7);
+        let verdict = detector
+            .detect(&DetectionContext::new(&message, db.clone(), 0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(verdict.kind, VerdictKind::ScamMessage);
+        assert_eq!(verdict.reason, "forwarded from scammer 7");
+
+        let message = from_user(8);
+        assert!(detector
+            .detect(&DetectionContext::new(&message, db, 0))
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_forward_from_source_over_threshold() {
+        let db = test_utils::database();
+        let scam_forwards = test_utils::rules()
+            .get()
+            .scoring
+            .forward_sources
+            .scam_forwards;
+        for (chat_id, count) in [(-300, scam_forwards), (-400, scam_forwards - 1)] {
+            let mut source = ForwardSource::new(chat_id, 0);
+            source.scam_forwards = count;
+            db.lock().unwrap().save(&source).unwrap();
+        }
+        let detector = ForwardDetector::new(test_utils::rules());
+
+        let message = from_channel(-300);
+        let verdict = detector
+            .detect(&DetectionContext::new(&message, db.clone(), 0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(verdict.reason.starts_with("forwarded from chat -300"));
+
+        let message = from_channel(-400);
+        assert!(detector
+            .detect(&DetectionContext::new(&message, db, 0))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,99 @@
+use async_trait::async_trait;
+
+use crate::{
+    error::FetishResult,
+    mentions::{self, Mention},
+    models::{scam_handle::ScamHandle, scammer::Scammer, user_wrapper::UserWrapper},
+};
+
+use super::{message_text, DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+const HANDLE_WEIGHT: f64 = 1.;
+
+/// Flags messages sending people to a known scam handle or to a known scammer, listed handles
+/// count even without '@'
+pub struct HandleDetector;
+
+#[async_trait]
+impl ScamDetector for HandleDetector {
+    fn name(&self) -> &'static str {
+        "handle"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let Some(text) = message_text(context.message) else {
+            return Ok(None);
+        };
+        let mut reasons = Vec::new();
+        {
+            let db = context.db.lock().unwrap();
+            let scam_handles = ScamHandle::select_active(db.connection())?;
+            for mention in mentions::extract_mentions(text) {
+                match mention {
+                    Mention::Username(username) => {
+                        if scam_handles.contains(&username) {
+                            reasons.push(format!("@{username}"));
+                        }
+                    }
+                    Mention::User(user_id) => {
+                        let is_scammer = db.load::<Scammer>(user_id)?.is_some();
+                        let has_scam_handle =
+                            db.load::<UserWrapper>(user_id)?.is_some_and(|user| {
+                                user.usernames
+                                    .iter()
+                                    .flat_map(|usernames| usernames.active_usernames.iter())
+                                    .any(|username| {
+                                        scam_handles.contains(&ScamHandle::normalize(username))
+                                    })
+                            });
+                        if is_scammer || has_scam_handle {
+                            reasons.push(format!("user {user_id}"));
+                        }
+                    }
+                }
+            }
+            // Handles written without '@' used to be caught by plain keywords
+            for username in mentions::bare_usernames(&text.text) {
+                let reason = format!("@{username}");
+                if !reasons.contains(&reason) && scam_handles.contains(&username) {
+                    reasons.push(reason);
+                }
+            }
+        }
+        if reasons.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Verdict::new(
+            VerdictKind::ScamMessage,
+            HANDLE_WEIGHT * reasons.len() as f64,
+            reasons.join(", "),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_seeded_handles_match_with_or_without_at() {
+        let db = test_utils::database();
+        for (text, expected) in [
+            ("Écris à @MrWarren65", Some("@mrwarren65")),
+            (
+                "Contactez MRWARREN65 ou bigboss097",
+                Some("@mrwarren65, @bigboss097"),
+            ),
+            ("Contactez moi directement", None),
+        ] {
+            let message = test_utils::message(-100, 1, 42, test_utils::text(text));
+            let verdict = HandleDetector
+                .detect(&DetectionContext::new(&message, db.clone(), 0))
+                .await
+                .unwrap();
+            assert_eq!(verdict.map(|verdict| verdict.reason).as_deref(), expected);
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::time;
+
+use async_trait::async_trait;
+use log::{debug, warn};
+use tdlib::{enums::MessageSender, types::MessageSenderUser};
+
+use crate::{
+    error::FetishResult,
+    image_hash::{self, PerceptualHash},
+    media::DownloadPolicy,
+    models::{chat_policy::ChatPolicy, image_fingerprint::ImageFingerprint},
+};
+
+use super::{DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+const IMAGE_WEIGHT: f64 = 1.;
+/// How long the pipeline waits for a download, slower images are still hashed in the background
+/// and matched from their fingerprint when they are posted again
+const INDEX_TIMEOUT_SECONDS: u64 = 5;
+
+/// Flags photos, video thumbnails and sender profile photos close to a reference image or to an
+/// image already seen with a listed scammer
+///
+/// Message images are only downloaded when the download policy allows it.
+pub struct ImageDetector {
+    download_policy: DownloadPolicy,
+}
+
+impl ImageDetector {
+    pub fn new(download_policy: DownloadPolicy) -> Self {
+        Self { download_policy }
+    }
+}
+
+#[async_trait]
+impl ScamDetector for ImageDetector {
+    fn name(&self) -> &'static str {
+        "image"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let user_id = match context.message.sender_id {
+            MessageSender::User(MessageSenderUser { user_id }) => user_id,
+            MessageSender::Chat(_) => 0,
+        };
+
+        let monitored = ChatPolicy::is_monitored(
+            context.message.chat_id,
+            context.db.lock().unwrap().connection(),
+        )?;
+        let mut hashes = vec![];
+        if let Some((kind, file)) = self
+            .download_policy
+            .image_to_hash(monitored, &context.message.content)
+        {
+            let indexing = tokio::spawn({
+                let (db, file) = (context.db.clone(), file.clone());
+                let (chat_id, message_id) = (context.message.chat_id, context.message.id);
+                let client_id = context.client_id;
+                async move {
+                    image_hash::index_image(db, &file, chat_id, message_id, user_id, client_id)
+                        .await
+                }
+            });
+            match tokio::time::timeout(time::Duration::from_secs(INDEX_TIMEOUT_SECONDS), indexing)
+                .await
+            {
+                Ok(Ok(Ok(hash))) => hashes.push((kind, hash)),
+                // Other detectors still get their say on the message
+                Ok(Ok(Err(e))) => warn!(
+                    "Could not hash the {kind} of message {}: {e:?}",
+                    context.message.id
+                ),
+                Ok(Err(e)) => warn!(
+                    "Could not hash the {kind} of message {}: {e:?}",
+                    context.message.id
+                ),
+                Err(_) => debug!(
+                    "The {kind} of message {} is still being hashed",
+                    context.message.id
+                ),
+            }
+        }
+
+        let db = context.db.lock().unwrap();
+        if user_id != 0 {
+            hashes.extend(
+                ImageFingerprint::select_profile_photos(user_id, db.connection())?
+                    .iter()
+                    .map(|fingerprint| ("profile photo", fingerprint.hash())),
+            );
+        }
+        if hashes.is_empty() {
+            return Ok(None);
+        }
+
+        let known_images = ImageFingerprint::select_references(db.connection())?
+            .into_iter()
+            .map(|fingerprint| {
+                let origin = format!(
+                    "reference image '{}'",
+                    fingerprint.reference_name().unwrap_or_default()
+                );
+                (origin, fingerprint.hash())
+            })
+            .chain(
+                ImageFingerprint::select_by_scammers(db.connection())?
+                    .into_iter()
+                    .map(|(fingerprint, scammer_id)| {
+                        (format!("scammer {scammer_id}"), fingerprint.hash())
+                    }),
+            )
+            .collect::<Vec<(String, PerceptualHash)>>();
+
+        let Some((kind, origin, distance)) = hashes
+            .iter()
+            .flat_map(|(kind, hash)| {
+                known_images
+                    .iter()
+                    .map(move |(origin, known)| (*kind, origin, hash.distance(known)))
+            })
+            .filter(|(_, _, distance)| *distance <= image_hash::MAX_DISTANCE)
+            .min_by_key(|(_, _, distance)| *distance)
+        else {
+            return Ok(None);
+        };
+        Ok(Some(
+            Verdict::new(
+                VerdictKind::ScamMessage,
+                IMAGE_WEIGHT,
+                format!("{kind} matches {origin} (distance {distance})"),
+            )
+            .with_categories(vec!["IMAGE".into()]),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+    };
+
+    use image::{DynamicImage, GrayImage, Luma};
+    use tdlib::{
+        enums::MessageContent,
+        types::{
+            File, FormattedText, LocalFile, Message, MessagePhoto, Photo, PhotoSize, RemoteFile,
+        },
+    };
+
+    use crate::test_utils;
+
+    use super::*;
+
+    fn gradient(offset: u8) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(64, 48, |x, y| {
+            Luma([(x * 3 + y).saturating_add(offset as u32).min(255) as u8])
+        }))
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("fetish-{}-{name}", std::process::id()))
+    }
+
+    /// A photo message whose file is already downloaded at `path`
+    fn photo_message(path: &Path, unique_id: &str) -> Message {
+        let file = File {
+            id: 1,
+            size: 12,
+            local: LocalFile {
+                path: path.to_string_lossy().into(),
+                is_downloading_completed: true,
+                ..Default::default()
+            },
+            remote: RemoteFile {
+                unique_id: unique_id.into(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let content = MessageContent::MessagePhoto(MessagePhoto {
+            photo: Photo {
+                sizes: vec![PhotoSize {
+                    photo: file,
+                    width: 10,
+                    height: 10,
+                    ..Default::default()
+                }],
+                ..Default::default()
+            },
+            caption: FormattedText::default(),
+            has_spoiler: false,
+            is_secret: false,
+        });
+        test_utils::message(-100, 1, 42, content)
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_image_is_skipped() {
+        let path = temp_path("corrupt.jpg");
+        fs::write(&path, b"not an image").unwrap();
+        let message = photo_message(&path, "corrupt");
+
+        let verdict = ImageDetector::new(DownloadPolicy::default())
+            .detect(&DetectionContext::new(&message, test_utils::database(), 0))
+            .await
+            .unwrap();
+        assert!(verdict.is_none());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_photo_matches_reference_image() {
+        let db = test_utils::database();
+        db.lock()
+            .unwrap()
+            .save(&ImageFingerprint::reference(
+                "gradient.png",
+                PerceptualHash::new(&gradient(0)),
+                "gradient.png".into(),
+                0,
+            ))
+            .unwrap();
+
+        let path = temp_path("brightened.png");
+        gradient(20).save(&path).unwrap();
+        let message = photo_message(&path, "brightened");
+        let verdict = ImageDetector::new(DownloadPolicy::default())
+            .detect(&DetectionContext::new(&message, db.clone(), 0))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(verdict.kind, VerdictKind::ScamMessage);
+        assert!(verdict.reason.contains("reference image 'gradient.png'"));
+        fs::remove_file(&path).unwrap();
+
+        let path = temp_path("flipped.png");
+        gradient(0).fliph().save(&path).unwrap();
+        let message = photo_message(&path, "flipped");
+        let verdict = ImageDetector::new(DownloadPolicy::default())
+            .detect(&DetectionContext::new(&message, db, 0))
+            .await
+            .unwrap();
+        assert!(verdict.is_none());
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use log::{info, trace};
+
+use crate::{error::FetishResult, rules::RulesStore};
+
+use super::{message_text, DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+pub struct KeywordDetector {
+    rules: Arc<RulesStore>,
+}
+
+impl KeywordDetector {
+    pub fn new(rules: Arc<RulesStore>) -> Self {
+        Self { rules }
+    }
+}
+
+#[async_trait]
+impl ScamDetector for KeywordDetector {
+    fn name(&self) -> &'static str {
+        "keyword"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let (Some(raw_text), Some(text)) = (message_text(context.message), &context.text) else {
+            trace!("{:#?}", context.message.content);
+            return Ok(None);
+        };
+        info!("{}: {}", context.message.chat_id, raw_text.text);
+        let rules = self.rules.get();
+        let matches = rules
+            .keywords
+            .iter()
+            .filter(|keyword| keyword.is_match(text))
+            .collect::<Vec<_>>();
+        if matches.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            Verdict::new(
+                VerdictKind::ScamMessage,
+                matches.iter().map(|keyword| keyword.weight).sum(),
+                matches
+                    .iter()
+                    .map(|keyword| format!("{keyword} ({})", keyword.weight))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            )
+            .with_categories(
+                matches
+                    .iter()
+                    .filter_map(|keyword| keyword.category.clone())
+                    .collect(),
+            ),
+        ))
+    }
+}
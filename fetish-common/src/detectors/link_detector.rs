@@ -0,0 +1,48 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::{error::FetishResult, links, rules::RulesStore};
+
+use super::{DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+/// Any clickable link gives the LINK category, blocklisted domains also weigh on the score
+pub struct LinkDetector {
+    rules: Arc<RulesStore>,
+}
+
+impl LinkDetector {
+    pub fn new(rules: Arc<RulesStore>) -> Self {
+        Self { rules }
+    }
+}
+
+#[async_trait]
+impl ScamDetector for LinkDetector {
+    fn name(&self) -> &'static str {
+        "link"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let rules = self.rules.get();
+        let mut score = 0.;
+        let mut reasons = Vec::new();
+        for link in links::message_links(context.message) {
+            match rules.domains.lookup(&link.domain) {
+                Some(group) => {
+                    score += group.weight;
+                    reasons.push(format!("{} ({} {})", link.domain, group.name, group.weight));
+                }
+                None if link.is_explicit => reasons.push(link.domain),
+                None => {}
+            }
+        }
+        if reasons.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(
+            Verdict::new(VerdictKind::ScamMessage, score, reasons.join(", "))
+                .with_categories(vec!["LINK".into()]),
+        ))
+    }
+}
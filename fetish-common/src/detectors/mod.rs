@@ -0,0 +1,201 @@
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    sync::{Arc, Mutex},
+};
+
+use async_trait::async_trait;
+use log::debug;
+use serde::Serialize;
+use tdlib::{
+    enums::MessageContent,
+    types::{FormattedText, Message},
+};
+
+use crate::{database::Database, error::FetishResult, normalization::NormalizedText};
+
+pub mod forward_detector;
+pub mod handle_detector;
+pub mod image_detector;
+pub mod keyword_detector;
+pub mod link_detector;
+pub mod profile_detector;
+pub mod scammer_account_detector;
+pub mod telegram_flag_detector;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum VerdictKind {
+    ScamAccount,
+    ScamMessage,
+}
+
+impl Display for VerdictKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerdictKind::ScamAccount => write!(f, "scam_account"),
+            VerdictKind::ScamMessage => write!(f, "scam_message"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Verdict {
+    pub kind: VerdictKind,
+    pub source: &'static str,
+    pub score: f64,
+    pub reason: String,
+    pub categories: Vec<String>,
+    /// Adds to the score but can't get a message sanctioned on its own
+    pub is_supporting: bool,
+}
+
+impl Verdict {
+    pub fn new(kind: VerdictKind, score: f64, reason: impl Into<String>) -> Self {
+        Self {
+            kind,
+            source: "",
+            score,
+            reason: reason.into(),
+            categories: vec![],
+            is_supporting: false,
+        }
+    }
+
+    pub fn with_categories(mut self, categories: Vec<String>) -> Self {
+        self.categories = categories;
+        self
+    }
+
+    pub fn supporting(mut self) -> Self {
+        self.is_supporting = true;
+        self
+    }
+}
+
+/// Every verdict given on a message, the score breakdown is the list of verdicts
+#[derive(Debug, Default, Serialize)]
+pub struct Assessment {
+    pub verdicts: Vec<Verdict>,
+}
+
+impl Assessment {
+    pub fn score(&self) -> f64 {
+        self.verdicts.iter().map(|verdict| verdict.score).sum()
+    }
+
+    pub fn categories(&self) -> HashSet<&str> {
+        self.verdicts
+            .iter()
+            .flat_map(|verdict| verdict.categories.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Account verdicts are decisive, message verdicts have to reach the threshold together and
+    /// at least one of them must be about the message itself
+    pub fn sanction_kind(&self, threshold: f64) -> Option<VerdictKind> {
+        if self
+            .verdicts
+            .iter()
+            .any(|verdict| verdict.kind == VerdictKind::ScamAccount)
+        {
+            Some(VerdictKind::ScamAccount)
+        } else if self.verdicts.iter().any(|verdict| !verdict.is_supporting)
+            && self.score() >= threshold
+        {
+            Some(VerdictKind::ScamMessage)
+        } else {
+            None
+        }
+    }
+}
+
+impl Display for Assessment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.score())?;
+        for verdict in &self.verdicts {
+            write!(
+                f,
+                " [{} {}: {}]",
+                verdict.source, verdict.score, verdict.reason
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// What detectors get to look at, the text is normalized once before any detector runs
+pub struct DetectionContext<'a> {
+    pub message: &'a Message,
+    pub text: Option<NormalizedText>,
+    pub db: Arc<Mutex<Database>>,
+    pub client_id: i32,
+}
+
+impl<'a> DetectionContext<'a> {
+    pub fn new(message: &'a Message, db: Arc<Mutex<Database>>, client_id: i32) -> Self {
+        Self {
+            message,
+            text: message_text(message).map(|text| NormalizedText::new(&text.text)),
+            db,
+            client_id,
+        }
+    }
+}
+
+#[async_trait]
+pub trait ScamDetector: Sync + Send {
+    fn name(&self) -> &'static str;
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>>;
+}
+
+/// Runs detectors in the order they were added and gathers their verdicts
+#[derive(Default)]
+pub struct DetectorPipeline {
+    detectors: Vec<Box<dyn ScamDetector>>,
+}
+
+impl DetectorPipeline {
+    pub fn new() -> Self {
+        Self {
+            detectors: Vec::new(),
+        }
+    }
+
+    pub fn add_detector<Detector: ScamDetector + 'static>(mut self, detector: Detector) -> Self {
+        self.detectors.push(Box::new(detector));
+        self
+    }
+
+    pub async fn run(
+        &self,
+        message: &Message,
+        db: Arc<Mutex<Database>>,
+        client_id: i32,
+    ) -> FetishResult<Assessment> {
+        let context = DetectionContext::new(message, db, client_id);
+        let mut assessment = Assessment::default();
+        for detector in &self.detectors {
+            if let Some(mut verdict) = detector.detect(&context).await? {
+                debug!(
+                    "Detector '{}' flagged message {}: {}",
+                    detector.name(),
+                    message.id,
+                    verdict.reason
+                );
+                verdict.source = detector.name();
+                assessment.verdicts.push(verdict);
+            }
+        }
+        Ok(assessment)
+    }
+}
+
+pub fn message_text(message: &Message) -> Option<&FormattedText> {
+    match &message.content {
+        MessageContent::MessageText(message_text) => Some(&message_text.text),
+        MessageContent::MessagePhoto(message_photo) => Some(&message_photo.caption),
+        MessageContent::MessageVideo(message_video) => Some(&message_video.caption),
+        MessageContent::MessageAnimation(message_animation) => Some(&message_animation.caption),
+        _ => None,
+    }
+}
@@ -0,0 +1,129 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::Utc;
+use tdlib::{enums::MessageSender, types::MessageSenderUser, types::User};
+
+use crate::{
+    error::FetishResult, models::profile_assessment::ProfileAssessment,
+    normalization::NormalizedText, rules::RulesStore,
+};
+
+use super::{DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+/// Scores accounts on their display name and usernames
+///
+/// Profiles are assessed when TDLib sends the user, messages are then classified from the stored
+/// assessment. Profile verdicts only support content verdicts, a name alone never gets the
+/// messages of a user sanctioned.
+pub struct ProfileDetector {
+    rules: Arc<RulesStore>,
+}
+
+impl ProfileDetector {
+    pub fn new(rules: Arc<RulesStore>) -> Self {
+        Self { rules }
+    }
+
+    pub fn assess(&self, user: &User) -> ProfileAssessment {
+        let text = NormalizedText::new(&profile_text(user));
+        let rules = self.rules.get();
+        let matches = rules
+            .profile_keywords
+            .iter()
+            .filter(|keyword| keyword.is_match(&text))
+            .collect::<Vec<_>>();
+        ProfileAssessment {
+            user_id: user.id,
+            score: matches.iter().map(|keyword| keyword.weight).sum(),
+            reason: matches
+                .iter()
+                .map(|keyword| format!("{keyword} ({})", keyword.weight))
+                .collect::<Vec<_>>()
+                .join(", "),
+            assessed_at: Utc::now().timestamp(),
+        }
+    }
+}
+
+fn profile_text(user: &User) -> String {
+    let mut parts = vec![user.first_name.as_str(), user.last_name.as_str()];
+    if let Some(usernames) = &user.usernames {
+        parts.extend(usernames.active_usernames.iter().map(String::as_str));
+    }
+    parts.join(" ")
+}
+
+#[async_trait]
+impl ScamDetector for ProfileDetector {
+    fn name(&self) -> &'static str {
+        "profile"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let MessageSender::User(MessageSenderUser { user_id }) = context.message.sender_id else {
+            return Ok(None);
+        };
+        let Some(profile) = context
+            .db
+            .lock()
+            .unwrap()
+            .load::<ProfileAssessment>(user_id)?
+        else {
+            return Ok(None);
+        };
+        if profile.score <= 0. {
+            return Ok(None);
+        }
+        Ok(Some(
+            Verdict::new(VerdictKind::ScamMessage, profile.score, profile.reason)
+                .with_categories(vec!["PROFILE".into()])
+                .supporting(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{detectors::Assessment, test_utils};
+
+    use super::*;
+
+    #[test]
+    fn test_name_rules_match_whole_words() {
+        let detector = ProfileDetector::new(test_utils::rules());
+        assert_eq!(detector.assess(&test_utils::user(1, "Chérif")).score, 0.);
+        assert_eq!(detector.assess(&test_utils::user(2, "Chérie")).score, 0.5);
+        assert_eq!(
+            detector
+                .assess(&test_utils::user(3, "Escorte Coquine"))
+                .score,
+            2.
+        );
+    }
+
+    #[tokio::test]
+    async fn test_profile_alone_is_not_sanctioned() {
+        let db = test_utils::database();
+        let detector = ProfileDetector::new(test_utils::rules());
+        db.lock()
+            .unwrap()
+            .save(&detector.assess(&test_utils::user(42, "Escorte Coquine")))
+            .unwrap();
+        let message = test_utils::message(-100, 1, 42, test_utils::text("Bonjour à tous"));
+        let verdict = detector
+            .detect(&DetectionContext::new(&message, db, 0))
+            .await
+            .unwrap()
+            .unwrap();
+
+        let mut assessment = Assessment {
+            verdicts: vec![verdict],
+        };
+        assert_eq!(assessment.sanction_kind(1.), None);
+        assessment
+            .verdicts
+            .push(Verdict::new(VerdictKind::ScamMessage, 0.5, "DISPO"));
+        assert_eq!(assessment.sanction_kind(1.), Some(VerdictKind::ScamMessage));
+    }
+}
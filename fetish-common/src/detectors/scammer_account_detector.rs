@@ -0,0 +1,35 @@
+use async_trait::async_trait;
+use tdlib::{enums::MessageSender, types::MessageSenderUser};
+
+use crate::{error::FetishResult, models::scammer::Scammer};
+
+use super::{DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+pub struct ScammerAccountDetector;
+
+#[async_trait]
+impl ScamDetector for ScammerAccountDetector {
+    fn name(&self) -> &'static str {
+        "scammer_account"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let MessageSender::User(MessageSenderUser { user_id }) = context.message.sender_id else {
+            return Ok(None);
+        };
+        if context
+            .db
+            .lock()
+            .unwrap()
+            .load::<Scammer>(user_id)?
+            .is_none()
+        {
+            return Ok(None);
+        }
+        Ok(Some(Verdict::new(
+            VerdictKind::ScamAccount,
+            1.,
+            format!("user {user_id} is a known scammer"),
+        )))
+    }
+}
@@ -0,0 +1,107 @@
+use async_trait::async_trait;
+use tdlib::{
+    enums::{ChatType, MessageSender},
+    types::{ChatTypeSupergroup, MessageSenderChat, MessageSenderUser},
+};
+
+use crate::{
+    database::Database,
+    error::FetishResult,
+    models::{
+        chat_wrapper::ChatWrapper, supergroup_wrapper::SupergroupWrapper, user_wrapper::UserWrapper,
+    },
+};
+
+use super::{DetectionContext, ScamDetector, Verdict, VerdictKind};
+
+/// Senders Telegram itself marked as scam or fake are treated as known scammers
+pub struct TelegramFlagDetector;
+
+#[async_trait]
+impl ScamDetector for TelegramFlagDetector {
+    fn name(&self) -> &'static str {
+        "telegram_flag"
+    }
+
+    async fn detect(&self, context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+        let db = context.db.lock().unwrap();
+        let Some(flag) = telegram_flag(&db, &context.message.sender_id)? else {
+            return Ok(None);
+        };
+        let sender = match context.message.sender_id {
+            MessageSender::User(MessageSenderUser { user_id }) => format!("user {user_id}"),
+            MessageSender::Chat(MessageSenderChat { chat_id }) => format!("chat {chat_id}"),
+        };
+        Ok(Some(Verdict::new(
+            VerdictKind::ScamAccount,
+            1.,
+            format!("{sender} is flagged as {flag} by Telegram"),
+        )))
+    }
+}
+
+/// "scam" or "fake" when Telegram marked the user or the supergroup as such
+pub fn telegram_flag(db: &Database, sender: &MessageSender) -> FetishResult<Option<&'static str>> {
+    let (is_scam, is_fake) = match *sender {
+        MessageSender::User(MessageSenderUser { user_id }) => {
+            match db.load::<UserWrapper>(user_id)? {
+                Some(user) => (user.is_scam, user.is_fake),
+                None => return Ok(None),
+            }
+        }
+        MessageSender::Chat(MessageSenderChat { chat_id }) => {
+            let Some(chat) = db.load::<ChatWrapper>(chat_id)? else {
+                return Ok(None);
+            };
+            let ChatType::Supergroup(ChatTypeSupergroup { supergroup_id, .. }) = chat.r#type else {
+                return Ok(None);
+            };
+            match db.load::<SupergroupWrapper>(supergroup_id)? {
+                Some(supergroup) => (supergroup.is_scam, supergroup.is_fake),
+                None => return Ok(None),
+            }
+        }
+    };
+    Ok(match (is_scam, is_fake) {
+        (true, _) => Some("scam"),
+        (false, true) => Some("fake"),
+        (false, false) => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_flagged_sender_is_a_scam_account() {
+        let db = test_utils::database();
+        let mut scam = test_utils::user(1, "Scam");
+        scam.is_scam = true;
+        let mut fake = test_utils::user(2, "Fake");
+        fake.is_fake = true;
+        for user in [scam, fake, test_utils::user(3, "Regular")] {
+            db.lock().unwrap().save(&UserWrapper::from(user)).unwrap();
+        }
+
+        for (user_id, expected) in [
+            (1, Some("user 1 is flagged as scam by Telegram")),
+            (2, Some("user 2 is flagged as fake by Telegram")),
+            (3, None),
+            (4, None),
+        ] {
+            let message = test_utils::message(-100, 1, user_id, test_utils::text("Salut"));
+            let verdict = TelegramFlagDetector
+                .detect(&DetectionContext::new(&message, db.clone(), 0))
+                .await
+                .unwrap();
+            assert_eq!(
+                verdict.as_ref().map(|verdict| verdict.reason.as_str()),
+                expected
+            );
+            assert!(verdict.is_none_or(|verdict| verdict.kind == VerdictKind::ScamAccount));
+        }
+    }
+}
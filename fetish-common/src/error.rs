@@ -1,10 +1,28 @@
-use std::io;
+use std::{io, sync::OnceLock};
 
+use regex::Regex;
 use tdlib::types::Error;
 use tokio::{sync::broadcast::error::SendError, task::JoinError};
 
 pub type FetishResult<T> = Result<T, FetishError>;
 
+/// Seconds to wait before retrying after a flood error
+///
+/// The message of those 429 errors is "Too Many Requests: retry after {seconds}"
+pub fn retry_after(error: &Error) -> Option<u64> {
+    if error.code != 429 {
+        return None;
+    }
+    retry_after_regex()
+        .captures(&error.message)
+        .and_then(|captures| captures[1].parse().ok())
+}
+
+fn retry_after_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"after (\d+)").unwrap())
+}
+
 #[derive(Debug)]
 pub enum FetishError {
     Td(Error),
@@ -15,6 +33,8 @@ pub enum FetishError {
     SerdeJson(serde_json::Error),
     Dialoguer(dialoguer::Error),
     Rusqlite(rusqlite::Error),
+    Regex(regex::Error),
+    Image(image::ImageError),
 }
 
 impl From<Error> for FetishError {
@@ -58,3 +78,34 @@ impl From<rusqlite::Error> for FetishError {
         FetishError::Rusqlite(error)
     }
 }
+
+impl From<regex::Error> for FetishError {
+    fn from(error: regex::Error) -> Self {
+        FetishError::Regex(error)
+    }
+}
+
+impl From<image::ImageError> for FetishError {
+    fn from(error: image::ImageError) -> Self {
+        FetishError::Image(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after() {
+        let error = |code, message: &str| Error {
+            code,
+            message: message.into(),
+        };
+        assert_eq!(
+            retry_after(&error(429, "Too Many Requests: retry after 42")),
+            Some(42)
+        );
+        assert_eq!(retry_after(&error(429, "Too Many Requests")), None);
+        assert_eq!(retry_after(&error(400, "retry after 42")), None);
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use image::{imageops::FilterType, DynamicImage};
+use tdlib::types::File;
+
+use crate::{
+    database::Database,
+    error::FetishResult,
+    media,
+    models::{image_fingerprint::ImageFingerprint, image_sighting::ImageSighting},
+};
+
+/// Hamming distance under which two hashes are considered to be the same picture
+pub const MAX_DISTANCE: u32 = 10;
+
+/// Average and difference hashes of an image, both survive resizing and recompression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PerceptualHash {
+    pub average: u64,
+    pub difference: u64,
+}
+
+impl PerceptualHash {
+    pub fn new(image: &DynamicImage) -> Self {
+        let pixels = image.resize_exact(8, 8, FilterType::Triangle).to_luma8();
+        let mean = pixels.pixels().map(|pixel| pixel[0] as u64).sum::<u64>() / 64;
+        let average = pixels
+            .pixels()
+            .fold(0, |hash, pixel| hash << 1 | (pixel[0] as u64 > mean) as u64);
+
+        let pixels = image.resize_exact(9, 8, FilterType::Triangle).to_luma8();
+        let mut difference = 0;
+        for y in 0..8 {
+            for x in 0..8 {
+                let brighter = pixels.get_pixel(x + 1, y)[0] > pixels.get_pixel(x, y)[0];
+                difference = difference << 1 | brighter as u64;
+            }
+        }
+
+        Self {
+            average,
+            difference,
+        }
+    }
+
+    pub fn open(path: &Path) -> FetishResult<Self> {
+        Ok(Self::new(&image::open(path)?))
+    }
+
+    /// The worst of both hashes
+    pub fn distance(&self, other: &Self) -> u32 {
+        (self.average ^ other.average)
+            .count_ones()
+            .max((self.difference ^ other.difference).count_ones())
+    }
+
+    pub fn is_similar(&self, other: &Self) -> bool {
+        self.distance(other) <= MAX_DISTANCE
+    }
+}
+
+/// Downloads and hashes the file unless it already was, then records where it was seen
+pub async fn index_image(
+    db: Arc<Mutex<Database>>,
+    file: &File,
+    chat_id: i64,
+    message_id: i64,
+    user_id: i64,
+    client_id: i32,
+) -> FetishResult<PerceptualHash> {
+    let file_unique_id = file.remote.unique_id.clone();
+    let fingerprint = db
+        .lock()
+        .unwrap()
+        .load::<ImageFingerprint>(file_unique_id.clone())?;
+    let hash = match fingerprint {
+        Some(fingerprint) => fingerprint.hash(),
+        None => {
+            // The dispatcher downloads files ahead of time, most are already there
+            let path = if file.local.is_downloading_completed {
+                file.local.path.clone()
+            } else {
+                media::download(db.clone(), file, client_id)
+                    .await?
+                    .local
+                    .path
+            };
+            let hash = tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || PerceptualHash::open(Path::new(&path))
+            })
+            .await??;
+            db.lock().unwrap().save(&ImageFingerprint::new(
+                file_unique_id.clone(),
+                hash,
+                path,
+                Utc::now().timestamp(),
+            ))?;
+            hash
+        }
+    };
+    db.lock().unwrap().save(&ImageSighting {
+        file_unique_id,
+        chat_id,
+        message_id,
+        user_id,
+        seen_at: Utc::now().timestamp(),
+    })?;
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{GrayImage, Luma};
+
+    use super::*;
+
+    fn gradient(offset: u8) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_fn(64, 48, |x, y| {
+            Luma([(x * 3 + y).saturating_add(offset as u32).min(255) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_resized_and_brightened_images_match() {
+        let original = PerceptualHash::new(&gradient(0));
+        let resized = PerceptualHash::new(&gradient(0).resize_exact(320, 240, FilterType::Nearest));
+        let brightened = PerceptualHash::new(&gradient(20));
+        assert!(original.is_similar(&resized));
+        assert!(original.is_similar(&brightened));
+    }
+
+    #[test]
+    fn test_different_images_do_not_match() {
+        let original = PerceptualHash::new(&gradient(0));
+        let flipped = PerceptualHash::new(&gradient(0).fliph());
+        assert!(!original.is_similar(&flipped));
+    }
+}
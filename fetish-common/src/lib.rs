@@ -1,9 +1,18 @@
 pub mod application;
 pub mod database;
 pub mod database_resolve;
+pub mod detectors;
 pub mod error;
+pub mod image_hash;
+pub mod links;
 pub mod location;
+pub mod media;
+pub mod mentions;
 pub mod models;
+pub mod normalization;
+pub mod rules;
 pub mod scout;
 pub mod states;
+#[cfg(test)]
+mod test_utils;
 pub mod update_dispatcher;
@@ -0,0 +1,167 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tdlib::{
+    enums::{MessageContent, TextEntityType},
+    types::{FormattedText, Message, TextEntityTypeTextUrl},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub url: String,
+    pub domain: String,
+    /// Whether it is clickable for sure, bare domains found in the text may be false positives
+    pub is_explicit: bool,
+}
+
+impl Link {
+    fn new(url: &str, is_explicit: bool) -> Option<Self> {
+        Some(Self {
+            url: url.to_owned(),
+            domain: domain(url)?,
+            is_explicit,
+        })
+    }
+}
+
+/// Links of a message, from the URL and text link entities, the web page preview and the text
+pub fn message_links(message: &Message) -> Vec<Link> {
+    let (text, web_page_url) = match &message.content {
+        MessageContent::MessageText(message_text) => (
+            &message_text.text,
+            message_text.web_page.as_ref().map(|web_page| &web_page.url),
+        ),
+        MessageContent::MessagePhoto(message_photo) => (&message_photo.caption, None),
+        MessageContent::MessageVideo(message_video) => (&message_video.caption, None),
+        MessageContent::MessageAnimation(message_animation) => (&message_animation.caption, None),
+        _ => return vec![],
+    };
+    let mut links = extract_links(text);
+    if let Some(link) = web_page_url.and_then(|url| Link::new(url, true)) {
+        if !links.contains(&link) {
+            links.push(link);
+        }
+    }
+    links
+}
+
+pub fn extract_links(text: &FormattedText) -> Vec<Link> {
+    let mut links = Vec::new();
+    for entity in &text.entities {
+        let link = match &entity.r#type {
+            TextEntityType::Url => {
+                Link::new(&entity_text(&text.text, entity.offset, entity.length), true)
+            }
+            TextEntityType::TextUrl(TextEntityTypeTextUrl { url }) => Link::new(url, true),
+            _ => None,
+        };
+        links.extend(link);
+    }
+    for candidate in link_regex().find_iter(&text.text) {
+        let candidate = candidate.as_str();
+        let is_explicit = candidate.contains("://") || candidate.to_lowercase().starts_with("www.");
+        if let Some(link) = Link::new(candidate, is_explicit) {
+            if !links.iter().any(|known| known.domain == link.domain) {
+                links.push(link);
+            }
+        }
+    }
+    links
+}
+
+/// Lowercase host without scheme, credentials, port and "www." prefix
+pub fn domain(url: &str) -> Option<String> {
+    let url = url.trim();
+    let url = match url.find("://") {
+        Some(index) => &url[index + 3..],
+        None => url,
+    };
+    let host = url.split(['/', '?', '#']).next()?;
+    let host = host.rsplit('@').next()?;
+    let host = host.split(':').next()?;
+    let host = host.trim_end_matches('.').to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host);
+    (host.contains('.') && !host.starts_with('.')).then(|| host.to_owned())
+}
+
+/// TDLib offsets and lengths are in UTF-16 code units
+pub(crate) fn entity_text(text: &str, offset: i32, length: i32) -> String {
+    let utf16 = text
+        .encode_utf16()
+        .skip(offset.max(0) as usize)
+        .take(length.max(0) as usize)
+        .collect::<Vec<u16>>();
+    String::from_utf16_lossy(&utf16)
+}
+
+fn link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?:[a-zA-Z][a-zA-Z0-9+.\-]*://)?(?:[a-zA-Z0-9](?:[a-zA-Z0-9\-]*[a-zA-Z0-9])?\.)+[a-zA-Z]{2,}\b(?:[/?#][^\s]*)?").unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tdlib::types::TextEntity;
+
+    use super::*;
+
+    #[test]
+    fn test_domain() {
+        assert_eq!(domain("HTTPS://Bit.ly/3xYz"), Some("bit.ly".into()));
+        assert_eq!(
+            domain("www.example.com:8080/path"),
+            Some("example.com".into())
+        );
+        assert_eq!(domain("https://user@II1.SU?x=1"), Some("ii1.su".into()));
+        assert_eq!(domain("tg://resolve?domain=someone"), None);
+        assert_eq!(domain("localhost"), None);
+    }
+
+    #[test]
+    fn test_extract_links_from_entities() {
+        // The emoji takes two UTF-16 code units
+        let text = FormattedText {
+            text: "😘 clique ici ou va sur u.to/abc".into(),
+            entities: vec![
+                TextEntity {
+                    offset: 3,
+                    length: 6,
+                    r#type: TextEntityType::TextUrl(TextEntityTypeTextUrl {
+                        url: "https://ii1.su/login".into(),
+                    }),
+                },
+                TextEntity {
+                    offset: 24,
+                    length: 8,
+                    r#type: TextEntityType::Url,
+                },
+            ],
+        };
+        let domains = extract_links(&text)
+            .into_iter()
+            .map(|link| (link.domain, link.is_explicit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            domains,
+            vec![("ii1.su".into(), true), ("u.to".into(), true)]
+        );
+    }
+
+    #[test]
+    fn test_extract_bare_links() {
+        let text = FormattedText {
+            text: "Contact: https://t.me/joinchat/x ou bitly.com/y. Fin.".into(),
+            entities: vec![],
+        };
+        let domains = extract_links(&text)
+            .into_iter()
+            .map(|link| (link.domain, link.is_explicit))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            domains,
+            vec![("t.me".into(), true), ("bitly.com".into(), false)]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    fs, io,
+    path::Path,
+    sync::{Arc, Mutex},
+    time,
+};
+
+use chrono::Utc;
+use log::{debug, error, info};
+use serde::Deserialize;
+use tdlib::{
+    enums::MessageContent,
+    functions,
+    types::{File, Photo, PhotoSize},
+};
+use tokio::sync::broadcast;
+
+use crate::{database::Database, error::FetishResult, models::downloaded_file::DownloadedFile};
+
+const DOWNLOAD_PRIORITY: i32 = 1;
+
+/// Content of `media.json`
+///
+/// ```json
+/// {
+///     "downloads": {
+///         "photo": "full",
+///         "video": "thumbnail",
+///         "animation": "thumbnail",
+///         "max_file_size": 10485760,
+///         "monitored_chats_only": true
+///     },
+///     "cache": { "max_age_days": 30, "max_total_size": 1073741824, "interval_seconds": 3600 }
+/// }
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct MediaConfig {
+    #[serde(default)]
+    pub downloads: DownloadPolicy,
+    #[serde(default)]
+    pub cache: CachePolicy,
+}
+
+impl MediaConfig {
+    /// Defaults apply when the file is missing
+    pub fn load(path: impl AsRef<Path>) -> FetishResult<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => Ok(serde_json::from_str(&content)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MediaDownload {
+    Skip,
+    Thumbnail,
+    Full,
+}
+
+/// Which files of new messages get downloaded ahead of time
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DownloadPolicy {
+    pub photo: MediaDownload,
+    pub video: MediaDownload,
+    pub animation: MediaDownload,
+    /// In bytes, bigger files are never downloaded
+    pub max_file_size: i64,
+    /// Only download in chats the exploitation state assesses, see
+    /// [`crate::models::chat_policy::ChatPolicy::is_monitored`]
+    pub monitored_chats_only: bool,
+}
+
+impl Default for DownloadPolicy {
+    fn default() -> Self {
+        Self {
+            photo: MediaDownload::Full,
+            video: MediaDownload::Thumbnail,
+            animation: MediaDownload::Thumbnail,
+            max_file_size: 10 * 1024 * 1024,
+            monitored_chats_only: true,
+        }
+    }
+}
+
+impl DownloadPolicy {
+    /// `monitored` tells whether the chat of the message is assessed
+    pub fn file_to_download<'a>(
+        &self,
+        monitored: bool,
+        content: &'a MessageContent,
+    ) -> Option<&'a File> {
+        if self.monitored_chats_only && !monitored {
+            return None;
+        }
+        let file = match content {
+            MessageContent::MessagePhoto(message_photo) => match self.photo {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail => smallest_photo_size(&message_photo.photo),
+                MediaDownload::Full => largest_photo_size(&message_photo.photo),
+            }
+            .map(|size| &size.photo),
+            MessageContent::MessageVideo(message_video) => match self.video {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail => message_video
+                    .video
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| &thumbnail.file),
+                MediaDownload::Full => Some(&message_video.video.video),
+            },
+            MessageContent::MessageAnimation(message_animation) => match self.animation {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail => message_animation
+                    .animation
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| &thumbnail.file),
+                MediaDownload::Full => Some(&message_animation.animation.animation),
+            },
+            _ => None,
+        }?;
+        self.allows(file).then_some(file)
+    }
+
+    /// The picture of a message the image detector hashes, videos and animations are only
+    /// hashed through their thumbnail
+    pub fn image_to_hash<'a>(
+        &self,
+        monitored: bool,
+        content: &'a MessageContent,
+    ) -> Option<(&'static str, &'a File)> {
+        if self.monitored_chats_only && !monitored {
+            return None;
+        }
+        let (kind, file) = match content {
+            MessageContent::MessagePhoto(message_photo) => match self.photo {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail => smallest_photo_size(&message_photo.photo),
+                MediaDownload::Full => largest_photo_size(&message_photo.photo),
+            }
+            .map(|size| ("photo", &size.photo)),
+            MessageContent::MessageVideo(message_video) => match self.video {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail | MediaDownload::Full => message_video
+                    .video
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| ("video thumbnail", &thumbnail.file)),
+            },
+            MessageContent::MessageAnimation(message_animation) => match self.animation {
+                MediaDownload::Skip => None,
+                MediaDownload::Thumbnail | MediaDownload::Full => message_animation
+                    .animation
+                    .thumbnail
+                    .as_ref()
+                    .map(|thumbnail| ("animation thumbnail", &thumbnail.file)),
+            },
+            _ => None,
+        }?;
+        self.allows(file).then_some((kind, file))
+    }
+
+    /// Profile photos follow the photo policy, user photos are always `monitored` as the user
+    /// may post in any chat
+    pub fn profile_photo_to_hash<'a>(
+        &self,
+        monitored: bool,
+        small: &'a File,
+        big: &'a File,
+    ) -> Option<&'a File> {
+        if self.monitored_chats_only && !monitored {
+            return None;
+        }
+        let file = match self.photo {
+            MediaDownload::Skip => return None,
+            MediaDownload::Thumbnail => small,
+            MediaDownload::Full => big,
+        };
+        self.allows(file).then_some(file)
+    }
+
+    fn allows(&self, file: &File) -> bool {
+        file.size.max(file.expected_size) <= self.max_file_size
+    }
+}
+
+/// How the cache cleaner keeps the downloaded files in check
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CachePolicy {
+    pub max_age_days: i64,
+    /// In bytes
+    pub max_total_size: i64,
+    pub interval_seconds: u64,
+}
+
+impl Default for CachePolicy {
+    fn default() -> Self {
+        Self {
+            max_age_days: 30,
+            max_total_size: 1024 * 1024 * 1024,
+            interval_seconds: 60 * 60,
+        }
+    }
+}
+
+pub fn largest_photo_size(photo: &Photo) -> Option<&PhotoSize> {
+    photo
+        .sizes
+        .iter()
+        .max_by_key(|size| size.width * size.height)
+}
+
+pub fn smallest_photo_size(photo: &Photo) -> Option<&PhotoSize> {
+    photo
+        .sizes
+        .iter()
+        .min_by_key(|size| size.width * size.height)
+}
+
+/// Waits for the download and records the file for the cache cleaner
+pub async fn download(db: Arc<Mutex<Database>>, file: &File, client_id: i32) -> FetishResult<File> {
+    let tdlib::enums::File::File(file) =
+        functions::download_file(file.id, DOWNLOAD_PRIORITY, 0, 0, true, client_id).await?;
+    db.lock().unwrap().save(&DownloadedFile {
+        file_id: file.id,
+        file_unique_id: file.remote.unique_id.clone(),
+        path: file.local.path.clone(),
+        size: file.local.downloaded_size,
+        downloaded_at: Utc::now().timestamp(),
+    })?;
+    Ok(file)
+}
+
+pub async fn clean_cache(
+    db: Arc<Mutex<Database>>,
+    policy: CachePolicy,
+    client_id: i32,
+    mut shutdown_rx: broadcast::Receiver<()>,
+) {
+    info!("Starting cache cleaner");
+    let mut interval = tokio::time::interval(time::Duration::from_secs(policy.interval_seconds));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                if let Err(e) = evict_files(&db, &policy, client_id).await {
+                    error!("Failed to clean the cache: {e:#?}");
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                debug!("Shutting down cache cleaner");
+                break;
+            }
+        }
+    }
+}
+
+/// Evicts files past their age first, then the oldest ones until the cache fits
+async fn evict_files(
+    db: &Arc<Mutex<Database>>,
+    policy: &CachePolicy,
+    client_id: i32,
+) -> FetishResult<()> {
+    let (evictable, mut total_size) = {
+        let db = db.lock().unwrap();
+        (
+            DownloadedFile::select_evictable(db.connection())?,
+            DownloadedFile::total_size(db.connection())?,
+        )
+    };
+    let expired_at = Utc::now().timestamp() - policy.max_age_days * 24 * 60 * 60;
+    let mut evicted = 0;
+    for file in evictable {
+        if file.downloaded_at >= expired_at && total_size <= policy.max_total_size {
+            break;
+        }
+        if let Err(e) = functions::delete_file(file.file_id, client_id).await {
+            debug!("Could not delete file {}: {e:?}", file.file_id);
+        }
+        file.delete(db.lock().unwrap().connection())?;
+        total_size -= file.size;
+        evicted += 1;
+    }
+    if evicted > 0 {
+        info!("Evicted {evicted} files from the cache, {total_size} bytes left");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let config =
+            serde_json::from_str::<MediaConfig>(r#"{ "downloads": { "video": "full" } }"#).unwrap();
+        assert_eq!(config.downloads.video, MediaDownload::Full);
+        assert_eq!(config.downloads.animation, MediaDownload::Thumbnail);
+        assert!(config.downloads.monitored_chats_only);
+        assert_eq!(config.cache.max_age_days, 30);
+    }
+
+    #[test]
+    fn test_profile_photo_follows_policy() {
+        let small = File {
+            id: 1,
+            size: 1024,
+            ..Default::default()
+        };
+        let big = File {
+            id: 2,
+            size: 20 * 1024 * 1024,
+            ..Default::default()
+        };
+        let mut policy = DownloadPolicy::default();
+        assert_eq!(policy.profile_photo_to_hash(true, &small, &big), None);
+        assert_eq!(policy.profile_photo_to_hash(false, &small, &small), None);
+        assert_eq!(
+            policy
+                .profile_photo_to_hash(true, &small, &small)
+                .map(|file| file.id),
+            Some(1)
+        );
+        policy.photo = MediaDownload::Thumbnail;
+        assert_eq!(
+            policy
+                .profile_photo_to_hash(true, &small, &big)
+                .map(|file| file.id),
+            Some(1)
+        );
+        policy.photo = MediaDownload::Skip;
+        assert_eq!(policy.profile_photo_to_hash(true, &small, &big), None);
+    }
+}
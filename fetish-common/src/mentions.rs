@@ -0,0 +1,115 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use tdlib::{
+    enums::TextEntityType,
+    types::{FormattedText, TextEntityTypeMentionName},
+};
+
+use crate::{links, models::scam_handle::ScamHandle};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Mention {
+    /// A public username, normalized like scam handles
+    Username(String),
+    /// A user mentioned by name, without username
+    User(i64),
+}
+
+/// Usernames from mention entities, `@username` tokens and t.me links, plus users mentioned by name
+pub fn extract_mentions(text: &FormattedText) -> Vec<Mention> {
+    let mut mentions = Vec::new();
+    let mut push = |mention: Mention| {
+        if !mentions.contains(&mention) {
+            mentions.push(mention);
+        }
+    };
+
+    for entity in &text.entities {
+        match &entity.r#type {
+            TextEntityType::Mention => push(Mention::Username(ScamHandle::normalize(
+                &links::entity_text(&text.text, entity.offset, entity.length),
+            ))),
+            TextEntityType::MentionName(TextEntityTypeMentionName { user_id }) => {
+                push(Mention::User(*user_id))
+            }
+            _ => {}
+        }
+    }
+    for captures in username_regex().captures_iter(&text.text) {
+        push(Mention::Username(ScamHandle::normalize(&captures[1])));
+    }
+    for link in links::extract_links(text) {
+        if !matches!(link.domain.as_str(), "t.me" | "telegram.me") {
+            continue;
+        }
+        if let Some(username) = link
+            .url
+            .split(['/', '?', '#'])
+            .skip_while(|part| !part.to_lowercase().ends_with(&link.domain))
+            .nth(1)
+            .filter(|username| username_regex().is_match(&format!("@{username}")))
+        {
+            push(Mention::Username(ScamHandle::normalize(username)));
+        }
+    }
+    mentions
+}
+
+/// Words that could be a username written without '@', normalized like scam handles
+pub fn bare_usernames(text: &str) -> Vec<String> {
+    let mut usernames = Vec::new();
+    for captures in bare_username_regex().captures_iter(text) {
+        let username = ScamHandle::normalize(&captures[1]);
+        if !usernames.contains(&username) {
+            usernames.push(username);
+        }
+    }
+    usernames
+}
+
+fn bare_username_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(?:^|[^@\w])([a-zA-Z][a-zA-Z0-9_]{3,31})\b").unwrap())
+}
+
+fn username_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"@([a-zA-Z][a-zA-Z0-9_]{3,31})\b").unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use tdlib::types::TextEntity;
+
+    use super::*;
+
+    #[test]
+    fn test_extract_mentions() {
+        let text = FormattedText {
+            text: "Écris à @MrWarren65 ou Natasha, ou https://t.me/BigBoss097 ou @bigboss097"
+                .into(),
+            entities: vec![TextEntity {
+                offset: 23,
+                length: 7,
+                r#type: TextEntityType::MentionName(TextEntityTypeMentionName { user_id: 42 }),
+            }],
+        };
+        assert_eq!(
+            extract_mentions(&text),
+            vec![
+                Mention::User(42),
+                Mention::Username("mrwarren65".into()),
+                Mention::Username("bigboss097".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bare_usernames() {
+        assert_eq!(
+            bare_usernames("Contact MrWarren65, mais pas @BigBoss097"),
+            vec!["contact", "mrwarren65", "mais"]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use std::{fmt::Display, str::FromStr};
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, ToSql,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// What the bot does with a chat, chats without a policy are warned
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Policy {
+    /// Messages are assessed and recorded, nothing is sent
+    Observe,
+    /// Scam messages get a reply
+    #[default]
+    Warn,
+    /// Scam messages are reported to Telegram
+    Report,
+    /// Messages are not even assessed
+    Ignore,
+}
+
+impl Policy {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Policy::Observe => "observe",
+            Policy::Warn => "warn",
+            Policy::Report => "report",
+            Policy::Ignore => "ignore",
+        }
+    }
+}
+
+impl Display for Policy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Policy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "observe" => Ok(Policy::Observe),
+            "warn" => Ok(Policy::Warn),
+            "report" => Ok(Policy::Report),
+            "ignore" => Ok(Policy::Ignore),
+            _ => Err(format!(
+                "unknown chat policy '{s}', expected observe, warn, report or ignore"
+            )),
+        }
+    }
+}
+
+impl ToSql for Policy {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for Policy {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ChatPolicy {
+    pub chat_id: i64,
+    pub policy: Policy,
+    pub updated_at: i64,
+}
+
+impl ChatPolicy {
+    /// Whether the exploitation state assesses the messages of the chat, private chats never are
+    pub fn is_monitored(chat_id: i64, conn: &rusqlite::Connection) -> FetishResult<bool> {
+        if chat_id >= 0 {
+            return Ok(false);
+        }
+        Ok(Self::select_by_id(chat_id, conn)?
+            .map(|chat_policy| chat_policy.policy)
+            .unwrap_or_default()
+            != Policy::Ignore)
+    }
+}
+
+impl AutoRequestable for ChatPolicy {
+    type UniqueIdentifier = i64;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS CHAT_POLICIES (
+            chat_id INTEGER PRIMARY KEY,
+            policy TEXT NOT NULL,
+            updated_at INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.chat_id
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM CHAT_POLICIES WHERE chat_id = :chat_id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM CHAT_POLICIES"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO CHAT_POLICIES (chat_id, policy, updated_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![self.chat_id, self.policy, self.updated_at],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE CHAT_POLICIES
+            SET
+                policy = ?2,
+                updated_at = ?3
+            WHERE
+                chat_id = ?1"#,
+            rusqlite::params![self.chat_id, self.policy, self.updated_at],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ChatPolicy, rusqlite::Error> {
+    Ok(ChatPolicy {
+        chat_id: row.get("chat_id")?,
+        policy: row.get("policy")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignored_and_private_chats_are_not_monitored() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&ChatPolicy::create_table_request(), [])
+            .unwrap();
+        ChatPolicy {
+            chat_id: -200,
+            policy: Policy::Ignore,
+            updated_at: 0,
+        }
+        .insert(&conn)
+        .unwrap();
+        ChatPolicy {
+            chat_id: -300,
+            policy: Policy::Observe,
+            updated_at: 0,
+        }
+        .insert(&conn)
+        .unwrap();
+
+        assert!(ChatPolicy::is_monitored(-100, &conn).unwrap());
+        assert!(!ChatPolicy::is_monitored(-200, &conn).unwrap());
+        assert!(ChatPolicy::is_monitored(-300, &conn).unwrap());
+        assert!(!ChatPolicy::is_monitored(42, &conn).unwrap());
+    }
+}
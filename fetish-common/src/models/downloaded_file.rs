@@ -0,0 +1,139 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// A file of the TDLib cache we asked for, the cache cleaner only ever evicts those
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DownloadedFile {
+    pub file_id: i32,
+    pub file_unique_id: String,
+    pub path: String,
+    pub size: i64,
+    pub downloaded_at: i64,
+}
+
+impl DownloadedFile {
+    pub fn total_size(conn: &rusqlite::Connection) -> FetishResult<i64> {
+        Ok(conn.query_row(
+            r#"SELECT COALESCE(SUM(size), 0) FROM DOWNLOADED_FILES"#,
+            rusqlite::named_params! {},
+            |row| row.get(0),
+        )?)
+    }
+
+    /// Oldest first, files showing a listed scammer or attached to scammer evidence are kept
+    pub fn select_evictable(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM DOWNLOADED_FILES WHERE file_unique_id NOT IN (
+                    SELECT s.file_unique_id FROM IMAGE_SIGHTINGS s
+                    LEFT JOIN SCAMMER_EVIDENCES e ON s.chat_id = e.chat_id AND s.message_id = e.message_id
+                    WHERE e.user_id IS NOT NULL OR s.user_id IN (SELECT user_id FROM SCAMMERS)
+                )
+                ORDER BY downloaded_at"#,
+            )?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    pub fn delete(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"DELETE FROM DOWNLOADED_FILES WHERE file_id = :file_id"#,
+            rusqlite::named_params! {
+                r#":file_id"#: self.file_id,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+impl AutoRequestable for DownloadedFile {
+    type UniqueIdentifier = i32;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS DOWNLOADED_FILES (
+            file_id INTEGER PRIMARY KEY,
+            file_unique_id TEXT NOT NULL,
+            path TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            downloaded_at INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.file_id
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM DOWNLOADED_FILES WHERE file_id = :file_id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":file_id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM DOWNLOADED_FILES"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO DOWNLOADED_FILES (file_id, file_unique_id, path, size, downloaded_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                self.file_id,
+                self.file_unique_id,
+                self.path,
+                self.size,
+                self.downloaded_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE DOWNLOADED_FILES
+            SET
+                file_unique_id = ?2,
+                path = ?3,
+                size = ?4,
+                downloaded_at = ?5
+            WHERE
+                file_id = ?1"#,
+            rusqlite::params![
+                self.file_id,
+                self.file_unique_id,
+                self.path,
+                self.size,
+                self.downloaded_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<DownloadedFile, rusqlite::Error> {
+    Ok(DownloadedFile {
+        file_id: row.get("file_id")?,
+        file_unique_id: row.get("file_unique_id")?,
+        path: row.get("path")?,
+        size: row.get("size")?,
+        downloaded_at: row.get("downloaded_at")?,
+    })
+}
@@ -0,0 +1,109 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// A chat or channel that scam messages were forwarded from
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForwardSource {
+    pub chat_id: i64,
+    /// Forwards from this chat that were sanctioned
+    pub scam_forwards: i64,
+    pub first_seen: i64,
+    pub last_seen: i64,
+}
+
+impl ForwardSource {
+    pub fn new(chat_id: i64, seen_at: i64) -> Self {
+        Self {
+            chat_id,
+            scam_forwards: 0,
+            first_seen: seen_at,
+            last_seen: seen_at,
+        }
+    }
+}
+
+impl AutoRequestable for ForwardSource {
+    type UniqueIdentifier = i64;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS FORWARD_SOURCES (
+            chat_id INTEGER PRIMARY KEY,
+            scam_forwards INTEGER NOT NULL,
+            first_seen INTEGER NOT NULL,
+            last_seen INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.chat_id
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM FORWARD_SOURCES WHERE chat_id = :chat_id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM FORWARD_SOURCES"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO FORWARD_SOURCES (chat_id, scam_forwards, first_seen, last_seen) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                self.chat_id,
+                self.scam_forwards,
+                self.first_seen,
+                self.last_seen,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE FORWARD_SOURCES
+            SET
+                scam_forwards = ?2,
+                first_seen = ?3,
+                last_seen = ?4
+            WHERE
+                chat_id = ?1"#,
+            rusqlite::params![
+                self.chat_id,
+                self.scam_forwards,
+                self.first_seen,
+                self.last_seen,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ForwardSource, rusqlite::Error> {
+    Ok(ForwardSource {
+        chat_id: row.get("chat_id")?,
+        scam_forwards: row.get("scam_forwards")?,
+        first_seen: row.get("first_seen")?,
+        last_seen: row.get("last_seen")?,
+    })
+}
@@ -0,0 +1,193 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::FetishResult, image_hash::PerceptualHash};
+
+use super::AutoRequestable;
+
+/// Reference images are not Telegram files, their id is their file name behind this prefix
+const REFERENCE_PREFIX: &str = "reference:";
+
+/// Perceptual hash of a downloaded image, keyed by the TDLib remote unique id of the file
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageFingerprint {
+    pub file_unique_id: String,
+    pub average_hash: i64,
+    pub difference_hash: i64,
+    pub path: String,
+    pub hashed_at: i64,
+}
+
+impl ImageFingerprint {
+    pub fn new(file_unique_id: String, hash: PerceptualHash, path: String, hashed_at: i64) -> Self {
+        Self {
+            file_unique_id,
+            average_hash: hash.average as i64,
+            difference_hash: hash.difference as i64,
+            path,
+            hashed_at,
+        }
+    }
+
+    pub fn reference(name: &str, hash: PerceptualHash, path: String, hashed_at: i64) -> Self {
+        Self::new(format!("{REFERENCE_PREFIX}{name}"), hash, path, hashed_at)
+    }
+
+    pub fn reference_name(&self) -> Option<&str> {
+        self.file_unique_id.strip_prefix(REFERENCE_PREFIX)
+    }
+
+    pub fn hash(&self) -> PerceptualHash {
+        PerceptualHash {
+            average: self.average_hash as u64,
+            difference: self.difference_hash as u64,
+        }
+    }
+
+    /// Images seen with a listed scammer, along with that scammer
+    pub fn select_by_scammers(conn: &rusqlite::Connection) -> FetishResult<Vec<(Self, i64)>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT DISTINCT f.*, s.user_id FROM IMAGE_FINGERPRINTS f
+                JOIN IMAGE_SIGHTINGS s ON f.file_unique_id = s.file_unique_id
+                JOIN SCAMMERS sc ON s.user_id = sc.user_id"#,
+            )?
+            .query_map(rusqlite::named_params! {}, |row| {
+                Ok((from_row(row)?, row.get::<_, i64>("user_id")?))
+            })?
+            .filter_map(Result::ok)
+            .collect::<Vec<(Self, i64)>>())
+    }
+
+    pub fn select_references(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM IMAGE_FINGERPRINTS WHERE file_unique_id LIKE :prefix"#)?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":prefix"#: format!("{REFERENCE_PREFIX}%"),
+                },
+                from_row,
+            )?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    /// The reference folder is indexed again from scratch on every start
+    pub fn delete_references(conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"DELETE FROM IMAGE_FINGERPRINTS WHERE file_unique_id LIKE :prefix"#,
+            rusqlite::named_params! {
+                r#":prefix"#: format!("{REFERENCE_PREFIX}%"),
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Profile photos of a user, as indexed from user updates
+    pub fn select_profile_photos(
+        user_id: i64,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT DISTINCT f.* FROM IMAGE_FINGERPRINTS f
+                JOIN IMAGE_SIGHTINGS s ON f.file_unique_id = s.file_unique_id
+                WHERE s.user_id = :user_id AND s.message_id = 0"#,
+            )?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":user_id"#: user_id,
+                },
+                from_row,
+            )?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+}
+
+impl AutoRequestable for ImageFingerprint {
+    type UniqueIdentifier = String;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS IMAGE_FINGERPRINTS (
+            file_unique_id TEXT PRIMARY KEY,
+            average_hash INTEGER NOT NULL,
+            difference_hash INTEGER NOT NULL,
+            path TEXT NOT NULL,
+            hashed_at INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.file_unique_id.clone()
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM IMAGE_FINGERPRINTS WHERE file_unique_id = :file_unique_id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":file_unique_id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM IMAGE_FINGERPRINTS"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO IMAGE_FINGERPRINTS (file_unique_id, average_hash, difference_hash, path, hashed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                self.file_unique_id,
+                self.average_hash,
+                self.difference_hash,
+                self.path,
+                self.hashed_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE IMAGE_FINGERPRINTS
+            SET
+                average_hash = ?2,
+                difference_hash = ?3,
+                path = ?4,
+                hashed_at = ?5
+            WHERE
+                file_unique_id = ?1"#,
+            rusqlite::params![
+                self.file_unique_id,
+                self.average_hash,
+                self.difference_hash,
+                self.path,
+                self.hashed_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ImageFingerprint, rusqlite::Error> {
+    Ok(ImageFingerprint {
+        file_unique_id: row.get("file_unique_id")?,
+        average_hash: row.get("average_hash")?,
+        difference_hash: row.get("difference_hash")?,
+        path: row.get("path")?,
+        hashed_at: row.get("hashed_at")?,
+    })
+}
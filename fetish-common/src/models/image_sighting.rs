@@ -0,0 +1,112 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// Where an image appeared, ids are 0 when not applicable (a profile photo has no message)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageSighting {
+    pub file_unique_id: String,
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub user_id: i64,
+    pub seen_at: i64,
+}
+
+impl AutoRequestable for ImageSighting {
+    type UniqueIdentifier = (String, i64, i64, i64);
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS IMAGE_SIGHTINGS (
+            file_unique_id TEXT NOT NULL,
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            user_id INTEGER NOT NULL,
+            seen_at INTEGER NOT NULL,
+            PRIMARY KEY (file_unique_id, chat_id, message_id, user_id)
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        (
+            self.file_unique_id.clone(),
+            self.chat_id,
+            self.message_id,
+            self.user_id,
+        )
+    }
+
+    fn select_by_id(
+        (file_unique_id, chat_id, message_id, user_id): Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM IMAGE_SIGHTINGS
+                WHERE file_unique_id = :file_unique_id AND chat_id = :chat_id AND message_id = :message_id AND user_id = :user_id"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":file_unique_id"#: file_unique_id,
+                    r#":chat_id"#: chat_id,
+                    r#":message_id"#: message_id,
+                    r#":user_id"#: user_id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM IMAGE_SIGHTINGS"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO IMAGE_SIGHTINGS (file_unique_id, chat_id, message_id, user_id, seen_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                self.file_unique_id,
+                self.chat_id,
+                self.message_id,
+                self.user_id,
+                self.seen_at,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE IMAGE_SIGHTINGS
+            SET
+                seen_at = ?5
+            WHERE
+                file_unique_id = ?1 AND chat_id = ?2 AND message_id = ?3 AND user_id = ?4"#,
+            rusqlite::params![
+                self.file_unique_id,
+                self.chat_id,
+                self.message_id,
+                self.user_id,
+                self.seen_at,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ImageSighting, rusqlite::Error> {
+    Ok(ImageSighting {
+        file_unique_id: row.get("file_unique_id")?,
+        chat_id: row.get("chat_id")?,
+        message_id: row.get("message_id")?,
+        user_id: row.get("user_id")?,
+        seen_at: row.get("seen_at")?,
+    })
+}
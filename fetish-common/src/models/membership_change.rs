@@ -0,0 +1,198 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tdlib::{enums::ChatMemberStatus, types::ChatMemberStatusRestricted};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// A change of our own status in a chat
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MembershipChange {
+    /// Assigned on insert, several changes can happen within the same second
+    pub id: Option<i64>,
+    pub chat_id: i64,
+    pub changed_at: i64,
+    pub old_status: ChatMemberStatus,
+    pub new_status: ChatMemberStatus,
+    /// The TDLib update that told us, "supergroup" or "basic_group"
+    pub source: String,
+}
+
+impl MembershipChange {
+    /// Banned, kicked or no longer allowed to post
+    pub fn is_lost(status: &ChatMemberStatus) -> bool {
+        match status {
+            ChatMemberStatus::Left | ChatMemberStatus::Banned(_) => true,
+            ChatMemberStatus::Restricted(ChatMemberStatusRestricted {
+                is_member,
+                permissions,
+                ..
+            }) => !is_member || !permissions.can_send_basic_messages,
+            ChatMemberStatus::Creator(_)
+            | ChatMemberStatus::Administrator(_)
+            | ChatMemberStatus::Member => false,
+        }
+    }
+
+    pub fn status_name(status: &ChatMemberStatus) -> &'static str {
+        match status {
+            ChatMemberStatus::Creator(_) => "creator",
+            ChatMemberStatus::Administrator(_) => "administrator",
+            ChatMemberStatus::Member => "member",
+            ChatMemberStatus::Restricted(_) => "restricted",
+            ChatMemberStatus::Left => "left",
+            ChatMemberStatus::Banned(_) => "banned",
+        }
+    }
+
+    pub fn select_by_chat_id(chat_id: i64, conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM MEMBERSHIP_CHANGES WHERE chat_id = :chat_id ORDER BY changed_at, id"#,
+            )?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":chat_id"#: chat_id,
+                },
+                from_row,
+            )?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+}
+
+impl AutoRequestable for MembershipChange {
+    type UniqueIdentifier = Option<i64>;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS MEMBERSHIP_CHANGES (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            chat_id INTEGER NOT NULL,
+            changed_at INTEGER NOT NULL,
+            old_status TEXT NOT NULL,
+            new_status TEXT NOT NULL,
+            source TEXT NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.id
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        Ok(conn
+            .prepare(r#"SELECT * FROM MEMBERSHIP_CHANGES WHERE id = :id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM MEMBERSHIP_CHANGES ORDER BY id"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO MEMBERSHIP_CHANGES (chat_id, changed_at, old_status, new_status, source) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![
+                self.chat_id,
+                self.changed_at,
+                serde_json::to_string(&self.old_status)?,
+                serde_json::to_string(&self.new_status)?,
+                self.source,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE MEMBERSHIP_CHANGES
+            SET
+                chat_id = ?2,
+                changed_at = ?3,
+                old_status = ?4,
+                new_status = ?5,
+                source = ?6
+            WHERE
+                id = ?1"#,
+            rusqlite::params![
+                self.id,
+                self.chat_id,
+                self.changed_at,
+                serde_json::to_string(&self.old_status)?,
+                serde_json::to_string(&self.new_status)?,
+                self.source,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<MembershipChange, rusqlite::Error> {
+    Ok(MembershipChange {
+        id: row.get("id")?,
+        chat_id: row.get("chat_id")?,
+        changed_at: row.get("changed_at")?,
+        old_status: serde_json::from_str(&row.get::<_, String>("old_status")?).unwrap(),
+        new_status: serde_json::from_str(&row.get::<_, String>("new_status")?).unwrap(),
+        source: row.get("source")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use tdlib::types::ChatMemberStatusBanned;
+
+    use super::*;
+
+    #[test]
+    fn test_changes_of_the_same_second_are_kept() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&MembershipChange::create_table_request(), [])
+            .unwrap();
+
+        let change = |old_status, new_status| MembershipChange {
+            id: None,
+            chat_id: -100,
+            changed_at: 42,
+            old_status,
+            new_status,
+            source: "supergroup".into(),
+        };
+        change(ChatMemberStatus::Member, ChatMemberStatus::Left)
+            .insert(&conn)
+            .unwrap();
+        change(
+            ChatMemberStatus::Left,
+            ChatMemberStatus::Banned(ChatMemberStatusBanned {
+                banned_until_date: 0,
+            }),
+        )
+        .insert(&conn)
+        .unwrap();
+
+        let changes = MembershipChange::select_by_chat_id(-100, &conn).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(
+            MembershipChange::status_name(&changes[1].new_status),
+            "banned"
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use tdlib::enums::MessageSender;
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct MessageAssessment {
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub score: f64,
+    pub threshold: f64,
+    pub is_scam: bool,
+    pub breakdown: String,
+    pub assessed_at: i64,
+    /// Assessed while sanctions were not sent, never counted as an offence
+    pub dry_run: bool,
+}
+
+impl MessageAssessment {
+    /// Scam assessments of the messages sent by `sender_id` in any chat since `since`, dry runs
+    /// excluded
+    pub fn select_scams_by_sender(
+        sender_id: &MessageSender,
+        since: i64,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT a.* FROM MESSAGE_ASSESSMENTS a
+                JOIN MESSAGES m ON a.message_id = m.message_id AND a.chat_id = m.chat_id
+                WHERE m.sender_id = :sender_id AND a.is_scam AND NOT a.dry_run AND a.assessed_at >= :since"#,
+            )?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":sender_id"#: serde_json::to_string(sender_id)?,
+                    r#":since"#: since,
+                },
+                from_row,
+            )?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+}
+
+impl AutoRequestable for MessageAssessment {
+    type UniqueIdentifier = (i64, i64);
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS MESSAGE_ASSESSMENTS (
+            message_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            score REAL NOT NULL,
+            threshold REAL NOT NULL,
+            is_scam BOOLEAN NOT NULL,
+            breakdown TEXT NOT NULL,
+            assessed_at INTEGER NOT NULL,
+            dry_run BOOLEAN NOT NULL DEFAULT FALSE,
+            PRIMARY KEY (chat_id, message_id)
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        (self.chat_id, self.message_id)
+    }
+
+    fn select_by_id(
+        (chat_id, message_id): Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM MESSAGE_ASSESSMENTS WHERE chat_id = :chat_id AND message_id = :message_id"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: chat_id,
+                    r#":message_id"#: message_id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM MESSAGE_ASSESSMENTS"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO MESSAGE_ASSESSMENTS (message_id, chat_id, score, threshold, is_scam, breakdown, assessed_at, dry_run) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                self.message_id,
+                self.chat_id,
+                self.score,
+                self.threshold,
+                self.is_scam,
+                self.breakdown,
+                self.assessed_at,
+                self.dry_run,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE MESSAGE_ASSESSMENTS
+            SET
+                score = ?3,
+                threshold = ?4,
+                is_scam = ?5,
+                breakdown = ?6,
+                assessed_at = ?7,
+                dry_run = ?8
+            WHERE
+                message_id = ?1 AND chat_id = ?2"#,
+            rusqlite::params![
+                self.message_id,
+                self.chat_id,
+                self.score,
+                self.threshold,
+                self.is_scam,
+                self.breakdown,
+                self.assessed_at,
+                self.dry_run,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<MessageAssessment, rusqlite::Error> {
+    Ok(MessageAssessment {
+        message_id: row.get("message_id")?,
+        chat_id: row.get("chat_id")?,
+        score: row.get("score")?,
+        threshold: row.get("threshold")?,
+        is_scam: row.get("is_scam")?,
+        breakdown: row.get("breakdown")?,
+        assessed_at: row.get("assessed_at")?,
+        dry_run: row.get("dry_run")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_message_id_in_two_chats() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&MessageAssessment::create_table_request(), [])
+            .unwrap();
+        let assessment = |chat_id, is_scam| MessageAssessment {
+            message_id: 7,
+            chat_id,
+            score: 2.,
+            threshold: 1.,
+            is_scam,
+            breakdown: "[]".into(),
+            assessed_at: 0,
+            dry_run: false,
+        };
+        assessment(-100, true).insert(&conn).unwrap();
+        assessment(-200, false).insert(&conn).unwrap();
+
+        assert!(
+            MessageAssessment::select_by_id((-100, 7), &conn)
+                .unwrap()
+                .unwrap()
+                .is_scam
+        );
+        assert!(
+            !MessageAssessment::select_by_id((-200, 7), &conn)
+                .unwrap()
+                .unwrap()
+                .is_scam
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::Deref;
+
 use rusqlite::OptionalExtension;
 use serde::{Serialize, Serializer};
 use tdlib::types::Message;
@@ -9,6 +11,14 @@ use super::AutoRequestable;
 #[derive(Debug)]
 pub struct MessageWrapper(Message);
 
+impl Deref for MessageWrapper {
+    type Target = Message;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
 impl From<Message> for MessageWrapper {
     fn from(message: Message) -> Self {
         Self(message)
@@ -24,12 +34,35 @@ impl Serialize for MessageWrapper {
     }
 }
 
+impl MessageWrapper {
+    /// Messages used to be keyed on their id alone, which is only unique within a chat
+    pub fn migrate(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        let key_columns = conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info('MESSAGES') WHERE pk > 0",
+            [],
+            |row| row.get::<_, i64>(0),
+        )?;
+        if key_columns > 1 {
+            return Ok(());
+        }
+        conn.execute_batch(&format!(
+            r#"BEGIN;
+            ALTER TABLE MESSAGES RENAME TO MESSAGES_OLD;
+            {};
+            INSERT INTO MESSAGES SELECT * FROM MESSAGES_OLD;
+            DROP TABLE MESSAGES_OLD;
+            COMMIT;"#,
+            Self::create_table_request()
+        ))
+    }
+}
+
 impl AutoRequestable for MessageWrapper {
-    type UniqueIdentifier = i64;
+    type UniqueIdentifier = (i64, i64);
 
     fn create_table_request() -> String {
         r#"CREATE TABLE IF NOT EXISTS MESSAGES (
-            message_id INTEGER PRIMARY KEY,
+            message_id INTEGER NOT NULL,
             sender_id INTEGER NOT NULL,
             chat_id INTEGER NOT NULL,
             sending_state TEXT,
@@ -66,21 +99,25 @@ impl AutoRequestable for MessageWrapper {
             media_album_id INTEGER NOT NULL,
             restriction_reason TEXT NOT NULL,
             content TEXT NOT NULL,
-            reply_markup TEXT
+            reply_markup TEXT,
+            PRIMARY KEY (chat_id, message_id)
         )"#
         .into()
     }
 
     fn get_id(&self) -> Self::UniqueIdentifier {
-        self.0.id
+        (self.0.chat_id, self.0.id)
     }
 
     fn select_by_id(id: Self::UniqueIdentifier, conn: &rusqlite::Connection) -> FetishResult<Option<Self>> {
         Ok(conn
-            .prepare(r#"SELECT * FROM MESSAGES WHERE message_id = :message_id"#)?
+            .prepare(
+                r#"SELECT * FROM MESSAGES WHERE chat_id = :chat_id AND message_id = :message_id"#,
+            )?
             .query_row(
                 rusqlite::named_params! {
-                    r#":message_id"#: id,
+                    r#":chat_id"#: id.0,
+                    r#":message_id"#: id.1,
                 },
                 from_row,
             )
@@ -264,7 +301,7 @@ impl AutoRequestable for MessageWrapper {
                 content = :content,
                 reply_markup = :reply_markup
             WHERE
-                message_id = :message_id"#
+                chat_id = :chat_id AND message_id = :message_id"#
                 .into(),
             rusqlite::named_params! {
                 ":message_id": &self.0.id,
@@ -355,3 +392,40 @@ fn from_row(row: &rusqlite::Row) -> Result<MessageWrapper, rusqlite::Error> {
         reply_markup: serde_json::from_str(&row.get::<_, String>("reply_markup")?).unwrap(),
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_keeps_messages_and_keys_them_per_chat() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        let keyed_on_message_id = MessageWrapper::create_table_request()
+            .replace(
+                "message_id INTEGER NOT NULL",
+                "message_id INTEGER PRIMARY KEY",
+            )
+            .replace(",\n            PRIMARY KEY (chat_id, message_id)", "");
+        conn.execute(&keyed_on_message_id, []).unwrap();
+        MessageWrapper::from(test_utils::message(-100, 1, 42, test_utils::text("hello")))
+            .insert(&conn)
+            .unwrap();
+
+        MessageWrapper::migrate(&conn).unwrap();
+        MessageWrapper::migrate(&conn).unwrap();
+        MessageWrapper::from(test_utils::message(-200, 1, 43, test_utils::text("hi")))
+            .insert(&conn)
+            .unwrap();
+
+        let first = MessageWrapper::select_by_id((-100, 1), &conn)
+            .unwrap()
+            .unwrap();
+        let second = MessageWrapper::select_by_id((-200, 1), &conn)
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.content, test_utils::text("hello"));
+        assert_eq!(second.content, test_utils::text("hi"));
+    }
+}
@@ -3,15 +3,32 @@ use rusqlite::Connection;
 use crate::error::FetishResult;
 
 use self::{
-    basic_group_wrapper::BasicGroupWrapper, chat_wrapper::ChatWrapper,
-    message_wrapper::MessageWrapper, scammer::Scammer, scouted_chat::ScoutedChat,
+    basic_group_wrapper::BasicGroupWrapper, chat_policy::ChatPolicy, chat_wrapper::ChatWrapper,
+    downloaded_file::DownloadedFile, forward_source::ForwardSource,
+    image_fingerprint::ImageFingerprint, image_sighting::ImageSighting,
+    membership_change::MembershipChange, message_assessment::MessageAssessment,
+    message_wrapper::MessageWrapper, profile_assessment::ProfileAssessment,
+    queued_action::QueuedAction, sanction::Sanction, scam_handle::ScamHandle, scammer::Scammer,
+    scammer_evidence::ScammerEvidence, scouted_chat::ScoutedChat,
     supergroup_wrapper::SupergroupWrapper, user_wrapper::UserWrapper,
 };
 
 pub mod basic_group_wrapper;
+pub mod chat_policy;
 pub mod chat_wrapper;
+pub mod downloaded_file;
+pub mod forward_source;
+pub mod image_fingerprint;
+pub mod image_sighting;
+pub mod membership_change;
+pub mod message_assessment;
 pub mod message_wrapper;
+pub mod profile_assessment;
+pub mod queued_action;
+pub mod sanction;
+pub mod scam_handle;
 pub mod scammer;
+pub mod scammer_evidence;
 pub mod scouted_chat;
 pub mod supergroup_wrapper;
 pub mod user_wrapper;
@@ -36,10 +53,42 @@ pub fn init_db(conn: &Connection) -> Result<(), rusqlite::Error> {
         &BasicGroupWrapper::create_table_request(),
         rusqlite::params![],
     )?;
+    conn.execute(&ChatPolicy::create_table_request(), rusqlite::params![])?;
     conn.execute(&ChatWrapper::create_table_request(), rusqlite::params![])?;
+    conn.execute(&DownloadedFile::create_table_request(), rusqlite::params![])?;
+    conn.execute(&ForwardSource::create_table_request(), rusqlite::params![])?;
+    conn.execute(
+        &ImageFingerprint::create_table_request(),
+        rusqlite::params![],
+    )?;
+    conn.execute(&ImageSighting::create_table_request(), rusqlite::params![])?;
+    conn.execute(
+        &MembershipChange::create_table_request(),
+        rusqlite::params![],
+    )?;
+    conn.execute(
+        &MessageAssessment::create_table_request(),
+        rusqlite::params![],
+    )?;
     conn.execute(&MessageWrapper::create_table_request(), rusqlite::params![])?;
+    MessageWrapper::migrate(conn)?;
+    conn.execute(
+        &ProfileAssessment::create_table_request(),
+        rusqlite::params![],
+    )?;
+    conn.execute(&QueuedAction::create_table_request(), rusqlite::params![])?;
+    conn.execute(&Sanction::create_table_request(), rusqlite::params![])?;
+    Sanction::migrate(conn)?;
+    conn.execute(&ScamHandle::create_table_request(), rusqlite::params![])?;
+    ScamHandle::seed(conn)?;
     conn.execute(&Scammer::create_table_request(), rusqlite::params![])?;
+    Scammer::migrate(conn)?;
+    conn.execute(
+        &ScammerEvidence::create_table_request(),
+        rusqlite::params![],
+    )?;
     conn.execute(&ScoutedChat::create_table_request(), rusqlite::params![])?;
+    ScoutedChat::migrate(conn)?;
     conn.execute(
         &SupergroupWrapper::create_table_request(),
         rusqlite::params![],
@@ -0,0 +1,87 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// Score of an account display name and usernames, refreshed on every user update
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProfileAssessment {
+    pub user_id: i64,
+    pub score: f64,
+    pub reason: String,
+    pub assessed_at: i64,
+}
+
+impl AutoRequestable for ProfileAssessment {
+    type UniqueIdentifier = i64;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS PROFILE_ASSESSMENTS (
+            user_id INTEGER PRIMARY KEY,
+            score REAL NOT NULL,
+            reason TEXT NOT NULL,
+            assessed_at INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.user_id
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM PROFILE_ASSESSMENTS WHERE user_id = :user_id"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":user_id"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM PROFILE_ASSESSMENTS"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO PROFILE_ASSESSMENTS (user_id, score, reason, assessed_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![self.user_id, self.score, self.reason, self.assessed_at],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE PROFILE_ASSESSMENTS
+            SET
+                score = ?2,
+                reason = ?3,
+                assessed_at = ?4
+            WHERE
+                user_id = ?1"#,
+            rusqlite::params![self.user_id, self.score, self.reason, self.assessed_at],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ProfileAssessment, rusqlite::Error> {
+    Ok(ProfileAssessment {
+        user_id: row.get("user_id")?,
+        score: row.get("score")?,
+        reason: row.get("reason")?,
+        assessed_at: row.get("assessed_at")?,
+    })
+}
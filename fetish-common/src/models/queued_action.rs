@@ -0,0 +1,247 @@
+use log::warn;
+use rusqlite::{types::Type, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tdlib::{enums::InputMessageContent, types::Message};
+
+use crate::error::FetishResult;
+
+use super::{sanction::SanctionMethod, AutoRequestable};
+
+/// What the message sender does about a sanctioned message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub enum OutgoingSanction {
+    Reply(InputMessageContent),
+    /// Reports the message with the given details, then its sender where Telegram allows it
+    Report(String),
+}
+
+impl OutgoingSanction {
+    pub fn method(&self) -> SanctionMethod {
+        match self {
+            OutgoingSanction::Reply(_) => SanctionMethod::Reply,
+            OutgoingSanction::Report(_) => SanctionMethod::Report,
+        }
+    }
+
+    /// Text kept in the sanction record
+    pub fn template(&self) -> String {
+        match self {
+            OutgoingSanction::Reply(InputMessageContent::InputMessageText(input)) => {
+                input.text.text.clone()
+            }
+            OutgoingSanction::Reply(_) => String::new(),
+            OutgoingSanction::Report(text) => text.clone(),
+        }
+    }
+}
+
+/// A sanction waiting for the message sender, kept until it went through so that it survives
+/// restarts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct QueuedAction {
+    pub chat_id: i64,
+    pub message_id: i64,
+    /// The sanctioned message
+    pub message: Message,
+    pub action: OutgoingSanction,
+    pub queued_at: i64,
+    /// Postponed by rate limits and retry after errors
+    pub not_before: i64,
+    pub attempts: i64,
+}
+
+impl QueuedAction {
+    pub fn new(message: Message, action: OutgoingSanction, queued_at: i64) -> Self {
+        Self {
+            chat_id: message.chat_id,
+            message_id: message.id,
+            message,
+            action,
+            queued_at,
+            not_before: queued_at,
+            attempts: 0,
+        }
+    }
+
+    /// Oldest first, rows that no longer deserialize are deleted so they can't clog the queue
+    pub fn select_due(
+        now: i64,
+        limit: usize,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Vec<Self>> {
+        let rows = conn
+            .prepare(
+                r#"SELECT * FROM ACTION_QUEUE WHERE not_before <= :now ORDER BY queued_at LIMIT :limit"#,
+            )?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":now"#: now,
+                    r#":limit"#: limit as i64,
+                },
+                |row| Ok((row.get("chat_id")?, row.get("message_id")?, from_row(row))),
+            )?
+            .collect::<Result<Vec<(i64, i64, _)>, _>>()?;
+        let mut actions = Vec::new();
+        for (chat_id, message_id, action) in rows {
+            match action {
+                Ok(action) => actions.push(action),
+                Err(e) => {
+                    warn!(
+                        "Dropping unreadable action on message {message_id} of chat {chat_id}: {e}"
+                    );
+                    delete_by_id(chat_id, message_id, conn)?;
+                }
+            }
+        }
+        Ok(actions)
+    }
+
+    pub fn delete(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        delete_by_id(self.chat_id, self.message_id, conn)
+    }
+}
+
+fn delete_by_id(chat_id: i64, message_id: i64, conn: &rusqlite::Connection) -> FetishResult<()> {
+    conn.execute(
+        r#"DELETE FROM ACTION_QUEUE WHERE chat_id = :chat_id AND message_id = :message_id"#,
+        rusqlite::named_params! {
+            r#":chat_id"#: chat_id,
+            r#":message_id"#: message_id,
+        },
+    )?;
+    Ok(())
+}
+
+impl AutoRequestable for QueuedAction {
+    type UniqueIdentifier = (i64, i64);
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS ACTION_QUEUE (
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            message TEXT NOT NULL,
+            action TEXT NOT NULL,
+            queued_at INTEGER NOT NULL,
+            not_before INTEGER NOT NULL,
+            attempts INTEGER NOT NULL,
+            PRIMARY KEY (chat_id, message_id)
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        (self.chat_id, self.message_id)
+    }
+
+    fn select_by_id(
+        (chat_id, message_id): Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM ACTION_QUEUE WHERE chat_id = :chat_id AND message_id = :message_id"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: chat_id,
+                    r#":message_id"#: message_id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM ACTION_QUEUE"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO ACTION_QUEUE (chat_id, message_id, message, action, queued_at, not_before, attempts) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                self.chat_id,
+                self.message_id,
+                serde_json::to_string(&self.message)?,
+                serde_json::to_string(&self.action)?,
+                self.queued_at,
+                self.not_before,
+                self.attempts,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE ACTION_QUEUE
+            SET
+                message = ?3,
+                action = ?4,
+                queued_at = ?5,
+                not_before = ?6,
+                attempts = ?7
+            WHERE
+                chat_id = ?1 AND message_id = ?2"#,
+            rusqlite::params![
+                self.chat_id,
+                self.message_id,
+                serde_json::to_string(&self.message)?,
+                serde_json::to_string(&self.action)?,
+                self.queued_at,
+                self.not_before,
+                self.attempts,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<QueuedAction, rusqlite::Error> {
+    Ok(QueuedAction {
+        chat_id: row.get("chat_id")?,
+        message_id: row.get("message_id")?,
+        message: from_json(row, "message")?,
+        action: from_json(row, "action")?,
+        queued_at: row.get("queued_at")?,
+        not_before: row.get("not_before")?,
+        attempts: row.get("attempts")?,
+    })
+}
+
+/// Queued rows outlive TDLib upgrades, what no longer parses is an error rather than a panic
+fn from_json<T: DeserializeOwned>(row: &rusqlite::Row, column: &str) -> Result<T, rusqlite::Error> {
+    serde_json::from_str(&row.get::<_, String>(column)?).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(
+            row.as_ref().column_index(column).unwrap_or_default(),
+            Type::Text,
+            Box::new(e),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_due_drops_unreadable_rows() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&QueuedAction::create_table_request(), [])
+            .unwrap();
+        conn.execute(
+            "INSERT INTO ACTION_QUEUE VALUES (-100, 1, '{}', '\"Unknown\"', 0, 0, 0)",
+            [],
+        )
+        .unwrap();
+
+        assert!(QueuedAction::select_by_id((-100, 1), &conn).is_err());
+        assert!(QueuedAction::select_due(10, 10, &conn).unwrap().is_empty());
+        assert!(QueuedAction::select_by_id((-100, 1), &conn)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,448 @@
+use std::{fmt::Display, str::FromStr};
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, ToSql,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SanctionStatus {
+    /// Queued for the message sender
+    Pending,
+    Sent,
+    Failed,
+    /// Never sent, on dry runs for instance
+    Skipped,
+}
+
+impl SanctionStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SanctionStatus::Pending => "pending",
+            SanctionStatus::Sent => "sent",
+            SanctionStatus::Failed => "failed",
+            SanctionStatus::Skipped => "skipped",
+        }
+    }
+}
+
+impl Display for SanctionStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SanctionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pending" => Ok(SanctionStatus::Pending),
+            "sent" => Ok(SanctionStatus::Sent),
+            "failed" => Ok(SanctionStatus::Failed),
+            "skipped" => Ok(SanctionStatus::Skipped),
+            _ => Err(format!("unknown sanction status '{s}'")),
+        }
+    }
+}
+
+impl ToSql for SanctionStatus {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for SanctionStatus {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+/// How the sanction reaches the chat
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum SanctionMethod {
+    /// A warning replying to the message
+    Reply,
+    /// The message and its sender are reported to Telegram
+    Report,
+}
+
+impl SanctionMethod {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SanctionMethod::Reply => "reply",
+            SanctionMethod::Report => "report",
+        }
+    }
+}
+
+impl Display for SanctionMethod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SanctionMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reply" => Ok(SanctionMethod::Reply),
+            "report" => Ok(SanctionMethod::Report),
+            _ => Err(format!("unknown sanction method '{s}'")),
+        }
+    }
+}
+
+impl ToSql for SanctionMethod {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for SanctionMethod {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+const MIGRATED_COLUMNS: [(&str, &str); 7] = [
+    ("status", "TEXT NOT NULL DEFAULT 'skipped'"),
+    ("error", "TEXT"),
+    ("sent_message_id", "INTEGER"),
+    ("sent_at", "INTEGER"),
+    ("sender_id", "INTEGER"),
+    ("content_key", "TEXT"),
+    ("method", "TEXT NOT NULL DEFAULT 'reply'"),
+];
+
+/// A sanction decided on a message and what became of it
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sanction {
+    pub chat_id: i64,
+    pub message_id: i64,
+    pub kind: String,
+    pub reason: String,
+    pub template: String,
+    pub is_dry_run: bool,
+    pub created_at: i64,
+    pub status: SanctionStatus,
+    /// TDLib error or why the sanction was skipped
+    pub error: Option<String>,
+    /// Temporary until TDLib confirms the message was sent
+    pub sent_message_id: Option<i64>,
+    pub sent_at: Option<i64>,
+    /// User id or chat id of the sanctioned sender
+    pub sender_id: Option<i64>,
+    /// Folded text of the sanctioned message, to spot the same ad posted by other accounts
+    pub content_key: Option<String>,
+    pub method: SanctionMethod,
+}
+
+impl Sanction {
+    /// Adds the columns missing from older databases, those created before outcome tracking
+    /// only hold dry runs
+    pub fn migrate(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        let columns = conn
+            .prepare("SELECT name FROM pragma_table_info('SANCTIONS')")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        for (name, definition) in MIGRATED_COLUMNS {
+            if !columns.iter().any(|column| column == name) {
+                conn.execute(
+                    &format!("ALTER TABLE SANCTIONS ADD COLUMN {name} {definition}"),
+                    [],
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Latest sanction of the chat, sent or about to be, for the same sender or the same content
+    ///
+    /// Dry runs only count as sent within other dry runs
+    pub fn select_recent_duplicate(
+        chat_id: i64,
+        sender_id: i64,
+        content_key: Option<&str>,
+        since: i64,
+        dry_run: bool,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM SANCTIONS
+                WHERE chat_id = :chat_id
+                    AND created_at >= :since
+                    AND (status IN ('pending', 'sent') OR (:dry_run AND is_dry_run AND error IS NULL))
+                    AND (sender_id = :sender_id OR content_key = :content_key)
+                ORDER BY created_at DESC
+                LIMIT 1"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: chat_id,
+                    r#":since"#: since,
+                    r#":sender_id"#: sender_id,
+                    r#":content_key"#: content_key,
+                    r#":dry_run"#: dry_run,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    /// Sent messages get their final id once the server acknowledged them
+    pub fn confirm_sent(
+        chat_id: i64,
+        old_message_id: i64,
+        message_id: i64,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SANCTIONS SET sent_message_id = :message_id
+            WHERE chat_id = :chat_id AND sent_message_id = :old_message_id"#,
+            rusqlite::named_params! {
+                r#":chat_id"#: chat_id,
+                r#":old_message_id"#: old_message_id,
+                r#":message_id"#: message_id,
+            },
+        )?;
+        Ok(())
+    }
+
+    pub fn mark_send_failed(
+        chat_id: i64,
+        old_message_id: i64,
+        error: &str,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SANCTIONS SET status = :status, error = :error
+            WHERE chat_id = :chat_id AND sent_message_id = :old_message_id"#,
+            rusqlite::named_params! {
+                r#":chat_id"#: chat_id,
+                r#":old_message_id"#: old_message_id,
+                r#":status"#: SanctionStatus::Failed,
+                r#":error"#: error,
+            },
+        )?;
+        Ok(())
+    }
+}
+
+impl AutoRequestable for Sanction {
+    type UniqueIdentifier = (i64, i64);
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS SANCTIONS (
+            chat_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            kind TEXT NOT NULL,
+            reason TEXT NOT NULL,
+            template TEXT NOT NULL,
+            is_dry_run BOOLEAN NOT NULL,
+            created_at INTEGER NOT NULL,
+            status TEXT NOT NULL DEFAULT 'skipped',
+            error TEXT,
+            sent_message_id INTEGER,
+            sent_at INTEGER,
+            sender_id INTEGER,
+            content_key TEXT,
+            method TEXT NOT NULL DEFAULT 'reply',
+            PRIMARY KEY (chat_id, message_id)
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        (self.chat_id, self.message_id)
+    }
+
+    fn select_by_id(
+        (chat_id, message_id): Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM SANCTIONS WHERE chat_id = :chat_id AND message_id = :message_id"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":chat_id"#: chat_id,
+                    r#":message_id"#: message_id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM SANCTIONS"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO SANCTIONS (chat_id, message_id, kind, reason, template, is_dry_run, created_at, status, error, sent_message_id, sent_at, sender_id, content_key, method) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            rusqlite::params![
+                self.chat_id,
+                self.message_id,
+                self.kind,
+                self.reason,
+                self.template,
+                self.is_dry_run,
+                self.created_at,
+                self.status,
+                self.error,
+                self.sent_message_id,
+                self.sent_at,
+                self.sender_id,
+                self.content_key,
+                self.method,
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SANCTIONS
+            SET
+                kind = ?3,
+                reason = ?4,
+                template = ?5,
+                is_dry_run = ?6,
+                created_at = ?7,
+                status = ?8,
+                error = ?9,
+                sent_message_id = ?10,
+                sent_at = ?11,
+                sender_id = ?12,
+                content_key = ?13,
+                method = ?14
+            WHERE
+                chat_id = ?1 AND message_id = ?2"#,
+            rusqlite::params![
+                self.chat_id,
+                self.message_id,
+                self.kind,
+                self.reason,
+                self.template,
+                self.is_dry_run,
+                self.created_at,
+                self.status,
+                self.error,
+                self.sent_message_id,
+                self.sent_at,
+                self.sender_id,
+                self.content_key,
+                self.method,
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<Sanction, rusqlite::Error> {
+    Ok(Sanction {
+        chat_id: row.get("chat_id")?,
+        message_id: row.get("message_id")?,
+        kind: row.get("kind")?,
+        reason: row.get("reason")?,
+        template: row.get("template")?,
+        is_dry_run: row.get("is_dry_run")?,
+        created_at: row.get("created_at")?,
+        status: row.get("status")?,
+        error: row.get("error")?,
+        sent_message_id: row.get("sent_message_id")?,
+        sent_at: row.get("sent_at")?,
+        sender_id: row.get("sender_id")?,
+        content_key: row.get("content_key")?,
+        method: row.get("method")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_dry_run_table() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE SANCTIONS (
+                chat_id INTEGER NOT NULL,
+                message_id INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                template TEXT NOT NULL,
+                is_dry_run BOOLEAN NOT NULL,
+                created_at INTEGER NOT NULL,
+                PRIMARY KEY (chat_id, message_id)
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO SANCTIONS VALUES (-100, 42, 'scam_message', '1', 'message', TRUE, 0)",
+            [],
+        )
+        .unwrap();
+
+        Sanction::migrate(&conn).unwrap();
+        Sanction::migrate(&conn).unwrap();
+
+        let sanction = Sanction::select_by_id((-100, 42), &conn).unwrap().unwrap();
+        assert_eq!(sanction.status, SanctionStatus::Skipped);
+        assert_eq!(sanction.sent_message_id, None);
+        assert_eq!(sanction.method, SanctionMethod::Reply);
+    }
+
+    #[test]
+    fn test_select_recent_duplicate() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&Sanction::create_table_request(), []).unwrap();
+        let sanction = Sanction {
+            chat_id: -100,
+            message_id: 42,
+            kind: "scam_message".into(),
+            reason: "1".into(),
+            template: "message".into(),
+            is_dry_run: false,
+            created_at: 1000,
+            status: SanctionStatus::Sent,
+            error: None,
+            sent_message_id: Some(43),
+            sent_at: Some(1001),
+            sender_id: Some(7),
+            content_key: Some("free crypto".into()),
+            method: SanctionMethod::Reply,
+        };
+        sanction.insert(&conn).unwrap();
+
+        let by_sender = Sanction::select_recent_duplicate(-100, 7, None, 500, false, &conn);
+        assert_eq!(by_sender.unwrap().unwrap().message_id, 42);
+        let by_content =
+            Sanction::select_recent_duplicate(-100, 8, Some("free crypto"), 500, false, &conn);
+        assert_eq!(by_content.unwrap().unwrap().message_id, 42);
+        let expired = Sanction::select_recent_duplicate(-100, 7, None, 1500, false, &conn);
+        assert!(expired.unwrap().is_none());
+        let other_chat = Sanction::select_recent_duplicate(-200, 7, None, 500, false, &conn);
+        assert!(other_chat.unwrap().is_none());
+    }
+}
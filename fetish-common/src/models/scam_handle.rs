@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// A username scammers send people to, stored lowercase without the '@'
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ScamHandle {
+    pub username: String,
+    pub is_active: bool,
+    pub added_at: i64,
+}
+
+/// Handles that were plain keywords before they had their own table
+const SEEDED_HANDLES: [&str; 4] = ["mrwarren65", "mrjackson2", "mralexander21", "bigboss097"];
+
+impl ScamHandle {
+    /// Handles that were removed from the CLI stay inactive
+    pub fn seed(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        for username in SEEDED_HANDLES {
+            conn.execute(
+                "INSERT OR IGNORE INTO SCAM_HANDLES (username, is_active, added_at) VALUES (?1, TRUE, strftime('%s', 'now'))",
+                rusqlite::params![username],
+            )?;
+        }
+        Ok(())
+    }
+
+    pub fn normalize(handle: &str) -> String {
+        handle.trim().trim_start_matches('@').to_lowercase()
+    }
+}
+
+impl ScamHandle {
+    /// Usernames of the active handles, looked up once per message
+    pub fn select_active(conn: &rusqlite::Connection) -> FetishResult<HashSet<String>> {
+        Ok(conn
+            .prepare(r#"SELECT username FROM SCAM_HANDLES WHERE is_active"#)?
+            .query_map(rusqlite::named_params! {}, |row| row.get(0))?
+            .collect::<Result<HashSet<String>, _>>()?)
+    }
+}
+
+impl AutoRequestable for ScamHandle {
+    type UniqueIdentifier = String;
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS SCAM_HANDLES (
+            username TEXT PRIMARY KEY,
+            is_active BOOLEAN NOT NULL,
+            added_at INTEGER NOT NULL
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        self.username.clone()
+    }
+
+    fn select_by_id(
+        id: Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM SCAM_HANDLES WHERE username = :username"#)?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":username"#: id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM SCAM_HANDLES"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO SCAM_HANDLES (username, is_active, added_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![self.username, self.is_active, self.added_at],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SCAM_HANDLES
+            SET
+                is_active = ?2,
+                added_at = ?3
+            WHERE
+                username = ?1"#,
+            rusqlite::params![self.username, self.is_active, self.added_at],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ScamHandle, rusqlite::Error> {
+    Ok(ScamHandle {
+        username: row.get("username")?,
+        is_active: row.get("is_active")?,
+        added_at: row.get("added_at")?,
+    })
+}
@@ -1,13 +1,117 @@
-use rusqlite::OptionalExtension;
+use std::{fmt::Display, str::FromStr};
+
+use rusqlite::{
+    types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef},
+    OptionalExtension, ToSql,
+};
 use serde::{Deserialize, Serialize};
 
 use crate::error::FetishResult;
 
 use super::AutoRequestable;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ScammerReason {
+    KeywordHit,
+    ManualFlag,
+    TelegramFlag,
+    Import,
+    /// Promoted after enough scam messages, whatever detectors flagged them
+    RepeatOffender,
+}
+
+impl ScammerReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ScammerReason::KeywordHit => "keyword_hit",
+            ScammerReason::ManualFlag => "manual_flag",
+            ScammerReason::TelegramFlag => "telegram_flag",
+            ScammerReason::Import => "import",
+            ScammerReason::RepeatOffender => "repeat_offender",
+        }
+    }
+}
+
+impl Display for ScammerReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ScammerReason {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keyword_hit" => Ok(ScammerReason::KeywordHit),
+            "manual_flag" => Ok(ScammerReason::ManualFlag),
+            "telegram_flag" => Ok(ScammerReason::TelegramFlag),
+            "import" => Ok(ScammerReason::Import),
+            "repeat_offender" => Ok(ScammerReason::RepeatOffender),
+            _ => Err(format!("unknown scammer reason '{s}'")),
+        }
+    }
+}
+
+impl ToSql for ScammerReason {
+    fn to_sql(&self) -> rusqlite::Result<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.as_str()))
+    }
+}
+
+impl FromSql for ScammerReason {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        value
+            .as_str()?
+            .parse()
+            .map_err(|e: String| FromSqlError::Other(e.into()))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Scammer {
     pub user_id: i64,
+    pub reason: ScammerReason,
+    /// What listed the account: "mojo2", "telegram", a detector name...
+    pub added_by: String,
+    pub first_seen: i64,
+    pub last_seen: i64,
+    pub hit_count: i64,
+}
+
+impl Scammer {
+    pub fn new(user_id: i64, reason: ScammerReason, added_by: &str, seen_at: i64) -> Self {
+        Self {
+            user_id,
+            reason,
+            added_by: added_by.to_owned(),
+            first_seen: seen_at,
+            last_seen: seen_at,
+            hit_count: 0,
+        }
+    }
+
+    /// Databases created before the metadata columns only have `user_id`, those rows were
+    /// flagged by hand from mojo2
+    pub fn migrate(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        let columns = conn
+            .prepare("SELECT name FROM pragma_table_info('SCAMMERS')")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.iter().any(|column| column == "reason") {
+            return Ok(());
+        }
+        conn.execute_batch(
+            r#"BEGIN;
+            ALTER TABLE SCAMMERS ADD COLUMN reason TEXT NOT NULL DEFAULT 'manual_flag';
+            ALTER TABLE SCAMMERS ADD COLUMN added_by TEXT NOT NULL DEFAULT 'mojo2';
+            ALTER TABLE SCAMMERS ADD COLUMN first_seen INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE SCAMMERS ADD COLUMN last_seen INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE SCAMMERS ADD COLUMN hit_count INTEGER NOT NULL DEFAULT 0;
+            UPDATE SCAMMERS SET first_seen = strftime('%s', 'now'), last_seen = strftime('%s', 'now');
+            COMMIT;"#,
+        )
+    }
 }
 
 impl AutoRequestable for Scammer {
@@ -15,7 +119,12 @@ impl AutoRequestable for Scammer {
 
     fn create_table_request() -> String {
         "CREATE TABLE IF NOT EXISTS SCAMMERS (
-            user_id INTEGER PRIMARY KEY
+            user_id INTEGER PRIMARY KEY,
+            reason TEXT NOT NULL DEFAULT 'manual_flag',
+            added_by TEXT NOT NULL DEFAULT 'mojo2',
+            first_seen INTEGER NOT NULL DEFAULT 0,
+            last_seen INTEGER NOT NULL DEFAULT 0,
+            hit_count INTEGER NOT NULL DEFAULT 0
         )"
         .into()
     }
@@ -34,7 +143,7 @@ impl AutoRequestable for Scammer {
                 rusqlite::named_params! {
                     r#":user_id"#: id,
                 },
-                |_| Ok(Scammer { user_id: id }),
+                from_row,
             )
             .optional()?)
     }
@@ -42,25 +151,80 @@ impl AutoRequestable for Scammer {
     fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
         Ok(conn
             .prepare(r#"SELECT * FROM SCAMMERS"#)?
-            .query_map(rusqlite::named_params! {}, |row| {
-                Ok(Scammer {
-                    user_id: row.get("user_id")?,
-                })
-            })?
-            .into_iter()
+            .query_map(rusqlite::named_params! {}, from_row)?
             .filter_map(Result::ok)
             .collect::<Vec<Self>>())
     }
 
     fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
         conn.execute(
-            "INSERT INTO SCAMMERS (user_id) VALUES (?1)",
-            rusqlite::params![self.user_id],
+            "INSERT INTO SCAMMERS (user_id, reason, added_by, first_seen, last_seen, hit_count) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                self.user_id,
+                self.reason,
+                self.added_by,
+                self.first_seen,
+                self.last_seen,
+                self.hit_count,
+            ],
         )?;
         Ok(())
     }
 
-    fn update(&self, _conn: &rusqlite::Connection) -> FetishResult<()> {
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SCAMMERS
+            SET
+                reason = ?2,
+                added_by = ?3,
+                first_seen = ?4,
+                last_seen = ?5,
+                hit_count = ?6
+            WHERE
+                user_id = ?1"#,
+            rusqlite::params![
+                self.user_id,
+                self.reason,
+                self.added_by,
+                self.first_seen,
+                self.last_seen,
+                self.hit_count,
+            ],
+        )?;
         Ok(())
     }
 }
+
+fn from_row(row: &rusqlite::Row) -> Result<Scammer, rusqlite::Error> {
+    Ok(Scammer {
+        user_id: row.get("user_id")?,
+        reason: row.get("reason")?,
+        added_by: row.get("added_by")?,
+        first_seen: row.get("first_seen")?,
+        last_seen: row.get("last_seen")?,
+        hit_count: row.get("hit_count")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_legacy_table() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute("CREATE TABLE SCAMMERS (user_id INTEGER PRIMARY KEY)", [])
+            .unwrap();
+        conn.execute("INSERT INTO SCAMMERS (user_id) VALUES (42)", [])
+            .unwrap();
+
+        Scammer::migrate(&conn).unwrap();
+        Scammer::migrate(&conn).unwrap();
+
+        let scammer = Scammer::select_by_id(42, &conn).unwrap().unwrap();
+        assert_eq!(scammer.reason, ScammerReason::ManualFlag);
+        assert_eq!(scammer.added_by, "mojo2");
+        assert_eq!(scammer.hit_count, 0);
+        assert!(scammer.first_seen > 0);
+    }
+}
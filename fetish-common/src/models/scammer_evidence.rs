@@ -0,0 +1,134 @@
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+
+use crate::error::FetishResult;
+
+use super::AutoRequestable;
+
+/// Links a listed scammer to one of its messages stored in `MESSAGES`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy)]
+pub struct ScammerEvidence {
+    pub user_id: i64,
+    pub message_id: i64,
+    pub chat_id: i64,
+    pub added_at: i64,
+}
+
+impl ScammerEvidence {
+    pub fn select_by_user_id(user_id: i64, conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM SCAMMER_EVIDENCES WHERE user_id = :user_id"#)?
+            .query_map(
+                rusqlite::named_params! {
+                    r#":user_id"#: user_id,
+                },
+                from_row,
+            )?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+}
+
+impl AutoRequestable for ScammerEvidence {
+    type UniqueIdentifier = (i64, i64, i64);
+
+    fn create_table_request() -> String {
+        "CREATE TABLE IF NOT EXISTS SCAMMER_EVIDENCES (
+            user_id INTEGER NOT NULL,
+            message_id INTEGER NOT NULL,
+            chat_id INTEGER NOT NULL,
+            added_at INTEGER NOT NULL,
+            PRIMARY KEY (user_id, chat_id, message_id)
+        )"
+        .into()
+    }
+
+    fn get_id(&self) -> Self::UniqueIdentifier {
+        (self.user_id, self.chat_id, self.message_id)
+    }
+
+    fn select_by_id(
+        (user_id, chat_id, message_id): Self::UniqueIdentifier,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<Option<Self>> {
+        Ok(conn
+            .prepare(
+                r#"SELECT * FROM SCAMMER_EVIDENCES WHERE user_id = :user_id AND chat_id = :chat_id AND message_id = :message_id"#,
+            )?
+            .query_row(
+                rusqlite::named_params! {
+                    r#":user_id"#: user_id,
+                    r#":chat_id"#: chat_id,
+                    r#":message_id"#: message_id,
+                },
+                from_row,
+            )
+            .optional()?)
+    }
+
+    fn select_all(conn: &rusqlite::Connection) -> FetishResult<Vec<Self>> {
+        Ok(conn
+            .prepare(r#"SELECT * FROM SCAMMER_EVIDENCES"#)?
+            .query_map(rusqlite::named_params! {}, from_row)?
+            .filter_map(Result::ok)
+            .collect::<Vec<Self>>())
+    }
+
+    fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            "INSERT INTO SCAMMER_EVIDENCES (user_id, message_id, chat_id, added_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![self.user_id, self.message_id, self.chat_id, self.added_at],
+        )?;
+        Ok(())
+    }
+
+    fn update(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SCAMMER_EVIDENCES
+            SET
+                added_at = ?4
+            WHERE
+                user_id = ?1 AND message_id = ?2 AND chat_id = ?3"#,
+            rusqlite::params![self.user_id, self.message_id, self.chat_id, self.added_at],
+        )?;
+        Ok(())
+    }
+}
+
+fn from_row(row: &rusqlite::Row) -> Result<ScammerEvidence, rusqlite::Error> {
+    Ok(ScammerEvidence {
+        user_id: row.get("user_id")?,
+        message_id: row.get("message_id")?,
+        chat_id: row.get("chat_id")?,
+        added_at: row.get("added_at")?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_message_id_in_two_chats() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(&ScammerEvidence::create_table_request(), [])
+            .unwrap();
+        for chat_id in [-100, -200] {
+            ScammerEvidence {
+                user_id: 42,
+                message_id: 7,
+                chat_id,
+                added_at: 0,
+            }
+            .insert(&conn)
+            .unwrap();
+        }
+        assert_eq!(
+            ScammerEvidence::select_by_user_id(42, &conn).unwrap().len(),
+            2
+        );
+        assert!(ScammerEvidence::select_by_id((42, -200, 7), &conn)
+            .unwrap()
+            .is_some());
+    }
+}
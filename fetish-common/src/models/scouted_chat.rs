@@ -11,6 +11,39 @@ pub struct ScoutedChat {
     pub location: Location,
     pub scouted_at: i64,
     pub joined_at: Option<i64>,
+    /// When we were banned, kicked or restricted in the chat, it is not joined again
+    pub lost_at: Option<i64>,
+}
+
+impl ScoutedChat {
+    /// Adds the lost_at column to databases created before membership tracking
+    pub fn migrate(conn: &rusqlite::Connection) -> Result<(), rusqlite::Error> {
+        let columns = conn
+            .prepare("SELECT name FROM pragma_table_info('SCOUTED_CHATS')")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if columns.iter().any(|column| column == "lost_at") {
+            return Ok(());
+        }
+        conn.execute("ALTER TABLE SCOUTED_CHATS ADD COLUMN lost_at INTEGER", [])?;
+        Ok(())
+    }
+
+    /// `None` marks the chat as active again, chats that were never scouted are left alone
+    pub fn set_lost_at(
+        chat_id: i64,
+        lost_at: Option<i64>,
+        conn: &rusqlite::Connection,
+    ) -> FetishResult<()> {
+        conn.execute(
+            r#"UPDATE SCOUTED_CHATS SET lost_at = :lost_at WHERE chat_id = :chat_id"#,
+            rusqlite::named_params! {
+                r#":chat_id"#: chat_id,
+                r#":lost_at"#: lost_at,
+            },
+        )?;
+        Ok(())
+    }
 }
 
 impl AutoRequestable for ScoutedChat {
@@ -21,7 +54,8 @@ impl AutoRequestable for ScoutedChat {
             chat_id INTEGER PRIMARY KEY,
             location TEXT NOT NULL,
             scouted_at INTEGER NOT NULL,
-            joined_at INTEGER
+            joined_at INTEGER,
+            lost_at INTEGER
         )"
         .into()
     }
@@ -56,12 +90,13 @@ impl AutoRequestable for ScoutedChat {
 
     fn insert(&self, conn: &rusqlite::Connection) -> FetishResult<()> {
         conn.execute(
-            "INSERT INTO SCOUTED_CHATS (chat_id, location, scouted_at, joined_at) VALUES (?1, ?2, ?3, ?4)",
+            "INSERT INTO SCOUTED_CHATS (chat_id, location, scouted_at, joined_at, lost_at) VALUES (?1, ?2, ?3, ?4, ?5)",
             rusqlite::params![
                 self.chat_id,
                 serde_json::to_string(&self.location).unwrap(),
                 self.scouted_at,
                 self.joined_at,
+                self.lost_at,
             ],
         )?;
         Ok(())
@@ -73,7 +108,8 @@ impl AutoRequestable for ScoutedChat {
             SET
                 location = ?2,
                 scouted_at = ?3,
-                joined_at = ?4
+                joined_at = ?4,
+                lost_at = ?5
             WHERE
                 chat_id = ?1"#
                 .into(),
@@ -82,6 +118,7 @@ impl AutoRequestable for ScoutedChat {
                 serde_json::to_string(&self.location).unwrap(),
                 self.scouted_at,
                 self.joined_at,
+                self.lost_at,
             ],
         )?;
         Ok(())
@@ -94,5 +131,50 @@ fn from_row(row: &rusqlite::Row) -> Result<ScoutedChat, rusqlite::Error> {
         location: serde_json::from_str(&row.get::<_, String>("location")?).unwrap(),
         scouted_at: row.get("scouted_at")?,
         joined_at: row.get("joined_at")?,
+        lost_at: row.get("lost_at")?,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_lost_at() {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute(
+            "CREATE TABLE SCOUTED_CHATS (
+                chat_id INTEGER PRIMARY KEY,
+                location TEXT NOT NULL,
+                scouted_at INTEGER NOT NULL,
+                joined_at INTEGER
+            )",
+            [],
+        )
+        .unwrap();
+        conn.execute(
+            "INSERT INTO SCOUTED_CHATS VALUES (-100, '[48.8, 2.3]', 0, 1)",
+            [],
+        )
+        .unwrap();
+
+        ScoutedChat::migrate(&conn).unwrap();
+        ScoutedChat::migrate(&conn).unwrap();
+        assert_eq!(
+            ScoutedChat::select_by_id(-100, &conn)
+                .unwrap()
+                .unwrap()
+                .lost_at,
+            None
+        );
+
+        ScoutedChat::set_lost_at(-100, Some(42), &conn).unwrap();
+        assert_eq!(
+            ScoutedChat::select_by_id(-100, &conn)
+                .unwrap()
+                .unwrap()
+                .lost_at,
+            Some(42)
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use std::sync::OnceLock;
+
+use regex::Regex;
+use unidecode::unidecode;
+
+/// Latin look-alikes that transliteration would otherwise turn into other letters (Cyrillic 'Р'
+/// becomes 'R' instead of 'P')
+const CONFUSABLES: [(char, char); 48] = [
+    ('А', 'A'),
+    ('В', 'B'),
+    ('Е', 'E'),
+    ('К', 'K'),
+    ('М', 'M'),
+    ('Н', 'H'),
+    ('О', 'O'),
+    ('Р', 'P'),
+    ('С', 'C'),
+    ('Т', 'T'),
+    ('Х', 'X'),
+    ('Ѕ', 'S'),
+    ('І', 'I'),
+    ('Ј', 'J'),
+    ('а', 'a'),
+    ('е', 'e'),
+    ('о', 'o'),
+    ('р', 'p'),
+    ('с', 'c'),
+    ('у', 'y'),
+    ('х', 'x'),
+    ('ѕ', 's'),
+    ('і', 'i'),
+    ('ј', 'j'),
+    ('ԁ', 'd'),
+    ('һ', 'h'),
+    ('Α', 'A'),
+    ('Β', 'B'),
+    ('Ε', 'E'),
+    ('Ζ', 'Z'),
+    ('Η', 'H'),
+    ('Ι', 'I'),
+    ('Κ', 'K'),
+    ('Μ', 'M'),
+    ('Ν', 'N'),
+    ('Ο', 'O'),
+    ('Ρ', 'P'),
+    ('Τ', 'T'),
+    ('Υ', 'Y'),
+    ('Χ', 'X'),
+    ('α', 'a'),
+    ('ι', 'i'),
+    ('κ', 'k'),
+    ('ν', 'v'),
+    ('ο', 'o'),
+    ('ρ', 'p'),
+    ('τ', 't'),
+    ('υ', 'u'),
+];
+
+const LEETSPEAK: [(char, char); 9] = [
+    ('0', 'O'),
+    ('1', 'I'),
+    ('3', 'E'),
+    ('4', 'A'),
+    ('5', 'S'),
+    ('7', 'T'),
+    ('8', 'B'),
+    ('@', 'A'),
+    ('$', 'S'),
+];
+
+/// Two views of a message text, detectors should try both
+///
+/// `plain` is the uppercased and transliterated text once invisible characters, look-alikes and
+/// letter emojis are folded, it keeps digits and punctuation so phone numbers, links and handles
+/// still match. `folded` additionally undoes leetspeak in words that are mostly letters, joins
+/// letters spelled out with separators and collapses letters repeated three times or more
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NormalizedText {
+    pub plain: String,
+    pub folded: String,
+}
+
+impl NormalizedText {
+    pub fn new(text: &str) -> Self {
+        let plain = plain(text);
+        let folded = fold(&plain);
+        Self { plain, folded }
+    }
+}
+
+fn plain(text: &str) -> String {
+    let text = text
+        .chars()
+        .filter(|c| !is_invisible(*c))
+        .map(fold_confusable)
+        .collect::<String>();
+    unidecode(&text.to_uppercase())
+}
+
+/// Folds an already `plain` text
+pub fn fold(plain: &str) -> String {
+    let text = plain
+        .split_inclusive(char::is_whitespace)
+        .map(|token| {
+            if is_mostly_digits(token.trim_end()) {
+                token.to_owned()
+            } else {
+                token.chars().map(fold_leetspeak).collect()
+            }
+        })
+        .collect::<String>();
+    let text = spelled_out_regex().replace_all(&text, |captures: &regex::Captures| {
+        captures[0]
+            .chars()
+            .filter(char::is_ascii_alphabetic)
+            .collect::<String>()
+    });
+    collapse_repeated_letters(&text)
+}
+
+fn is_invisible(c: char) -> bool {
+    matches!(
+        c,
+        '\u{00AD}'
+            | '\u{034F}'
+            | '\u{061C}'
+            | '\u{115F}'
+            | '\u{1160}'
+            | '\u{180E}'
+            | '\u{200B}'..='\u{200F}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2060}'..='\u{2064}'
+            | '\u{2066}'..='\u{2069}'
+            | '\u{20E3}'
+            | '\u{3164}'
+            | '\u{FE00}'..='\u{FE0F}'
+            | '\u{FEFF}'
+            | '\u{FFA0}'
+            | '\u{E0000}'..='\u{E007F}'
+    )
+}
+
+fn fold_confusable(c: char) -> char {
+    if let Some((_, latin)) = CONFUSABLES.iter().find(|(confusable, _)| *confusable == c) {
+        return *latin;
+    }
+    let letter = |offset: u32| char::from(b'A' + (offset % 26) as u8);
+    match c as u32 {
+        // Circled letters
+        code @ 0x24B6..=0x24E9 => letter(code - 0x24B6),
+        // Parenthesized, squared, negative circled and negative squared letters
+        code @ 0x1F110..=0x1F129 => letter(code - 0x1F110),
+        code @ 0x1F130..=0x1F149 => letter(code - 0x1F130),
+        code @ 0x1F150..=0x1F169 => letter(code - 0x1F150),
+        code @ 0x1F170..=0x1F189 => letter(code - 0x1F170),
+        // Regional indicators
+        code @ 0x1F1E6..=0x1F1FF => letter(code - 0x1F1E6),
+        // Mathematical alphanumeric symbols, 52 letters per style
+        code @ 0x1D400..=0x1D6A3 => letter((code - 0x1D400) % 52),
+        // Mathematical digits, 10 per style
+        code @ 0x1D7CE..=0x1D7FF => char::from(b'0' + ((code - 0x1D7CE) % 10) as u8),
+        _ => c,
+    }
+}
+
+fn fold_leetspeak(c: char) -> char {
+    LEETSPEAK
+        .iter()
+        .find(|(leet, _)| *leet == c)
+        .map_or(c, |(_, letter)| *letter)
+}
+
+/// Numbers, times and quantities such as "+33", "18H" or "2X" are not leetspeak
+fn is_mostly_digits(token: &str) -> bool {
+    let digits = token.chars().filter(char::is_ascii_digit).count();
+    digits > 0 && digits >= token.chars().filter(char::is_ascii_alphabetic).count()
+}
+
+/// Three letters or more separated by dots, dashes, spaces... like "E.S.C.O.R.T"
+fn spelled_out_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\b[A-Z](?:[ .\-_*|/~+]{1,2}[A-Z]\b){2,}").unwrap())
+}
+
+/// Doubled letters are common in plain words ("SALLE"), only longer runs are collapsed
+fn collapse_repeated_letters(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let chars = text.chars().collect::<Vec<char>>();
+    let mut start = 0;
+    while start < chars.len() {
+        let c = chars[start];
+        let end = start + chars[start..].iter().take_while(|&&next| next == c).count();
+        if c.is_ascii_alphabetic() && end - start >= 3 {
+            collapsed.push(c);
+        } else {
+            collapsed.extend(&chars[start..end]);
+        }
+        start = end;
+    }
+    collapsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Obfuscated samples seen in scam posts and the expected folded text
+    const CORPUS: [(&str, &str); 14] = [
+        ("3SC0RT dispo", "ESCORT DISPO"),
+        ("e.s.c.o.r.t", "ESCORT"),
+        ("E S C O R T sur Paris", "ESCORT SUR PARIS"),
+        ("s-n-a-p-c-h-a-t", "SNAPCHAT"),
+        ("ЕЅСОRТ", "ESCORT"),
+        ("ЕSCОRТ", "ESCORT"),
+        ("es\u{200B}co\u{200D}rt", "ESCORT"),
+        ("🅴🆂🅲🅾🆁🆃", "ESCORT"),
+        ("Ⓔⓢⓒⓞⓡⓣ", "ESCORT"),
+        ("𝐄𝐒𝐂𝐎𝐑𝐓", "ESCORT"),
+        ("ＥＳＣＯＲＴ", "ESCORT"),
+        ("ESSSCOOORTTT", "ESCORT"),
+        ("WH4TS4PP", "WHATSAPP"),
+        ("c0qu1ne ch3r1e", "COQUINE CHERIE"),
+    ];
+
+    #[test]
+    fn test_obfuscation_corpus() {
+        for (sample, expected) in CORPUS {
+            assert_eq!(NormalizedText::new(sample).folded, expected, "{sample}");
+        }
+    }
+
+    #[test]
+    fn test_plain_keeps_numbers_and_links() {
+        let text = NormalizedText::new("Écris moi au +33 6 12 34 56 78 ou https://bit.ly/x");
+        assert_eq!(
+            text.plain,
+            "ECRIS MOI AU +33 6 12 34 56 78 OU HTTPS://BIT.LY/X"
+        );
+        assert!(text.folded.contains("+33 6 12 34 56 78"));
+    }
+
+    #[test]
+    fn test_normal_text_is_kept() {
+        let text = NormalizedText::new("La salle est disponible à 18h, il y a un plan");
+        assert_eq!(text.plain, "LA SALLE EST DISPONIBLE A 18H, IL Y A UN PLAN");
+        assert_eq!(text.folded, text.plain);
+        let text = NormalizedText::new("Commande 2x, livrée le 3/12 à 18h30");
+        assert_eq!(text.folded, text.plain);
+    }
+}
@@ -0,0 +1,49 @@
+use serde::Deserialize;
+
+/// Content of `domains.json`, a domain also matches its subdomains
+///
+/// ```json
+/// [
+///     { "name": "shortener", "weight": 0.5, "domains": ["bit.ly", "u.to"] },
+///     { "name": "phishing", "weight": 1, "domains": ["ii1.su"] }
+/// ]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub struct DomainBlocklist {
+    groups: Vec<DomainGroup>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DomainGroup {
+    pub name: String,
+    pub weight: f64,
+    pub domains: Vec<String>,
+}
+
+impl DomainBlocklist {
+    pub fn lookup(&self, domain: &str) -> Option<&DomainGroup> {
+        self.groups.iter().find(|group| {
+            group.domains.iter().any(|listed| {
+                let listed = listed.to_lowercase();
+                domain == listed || domain.ends_with(&format!(".{listed}"))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        let blocklist = serde_json::from_str::<DomainBlocklist>(
+            r#"[{ "name": "shortener", "weight": 0.5, "domains": ["bit.ly"] }]"#,
+        )
+        .unwrap();
+        assert_eq!(blocklist.lookup("bit.ly").unwrap().name, "shortener");
+        assert!(blocklist.lookup("go.bit.ly").is_some());
+        assert!(blocklist.lookup("orbit.ly").is_none());
+    }
+}
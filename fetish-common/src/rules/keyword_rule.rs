@@ -0,0 +1,296 @@
+use std::fmt::Display;
+
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::normalization::{self, NormalizedText};
+
+/// A `keywords.json` entry, either a plain keyword or an extended rule
+///
+/// ```json
+/// [
+///     "BIT.LY",
+///     { "word": "DISPO", "weight": 0.5 },
+///     { "regex": "PLANS? CUL", "unless": [{ "word": "PLAN CULTUREL" }] },
+///     { "word": "PAYANT", "category": "PRICE" }
+/// ]
+/// ```
+///
+/// Patterns are matched against both views of the normalized message text, plain keywords weigh 1
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeywordRuleDefinition {
+    Plain(String),
+    Extended {
+        #[serde(flatten)]
+        pattern: PatternDefinition,
+        #[serde(default)]
+        unless: Vec<PatternDefinition>,
+        #[serde(default = "default_weight")]
+        weight: f64,
+        category: Option<String>,
+    },
+}
+
+impl KeywordRuleDefinition {
+    /// Plain keywords and `contains` patterns only match whole words, for short texts such as
+    /// names where "CHERI" must not match "CHERIF"
+    pub fn whole_words(self) -> Self {
+        match self {
+            KeywordRuleDefinition::Plain(keyword) => KeywordRuleDefinition::Extended {
+                pattern: PatternDefinition::Word(keyword),
+                unless: vec![],
+                weight: default_weight(),
+                category: None,
+            },
+            KeywordRuleDefinition::Extended {
+                pattern,
+                unless,
+                weight,
+                category,
+            } => KeywordRuleDefinition::Extended {
+                pattern: pattern.whole_word(),
+                unless: unless
+                    .into_iter()
+                    .map(PatternDefinition::whole_word)
+                    .collect(),
+                weight,
+                category,
+            },
+        }
+    }
+}
+
+fn default_weight() -> f64 {
+    1.
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternDefinition {
+    Contains(String),
+    Word(String),
+    Regex(String),
+}
+
+impl PatternDefinition {
+    fn whole_word(self) -> Self {
+        match self {
+            PatternDefinition::Contains(keyword) => PatternDefinition::Word(keyword),
+            pattern => pattern,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Matcher {
+    Contains(String),
+    Regex(Regex),
+}
+
+impl Matcher {
+    fn word(word: &str) -> Result<Self, regex::Error> {
+        Ok(Matcher::Regex(Regex::new(&format!(
+            r"\b{}\b",
+            regex::escape(word)
+        ))?))
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Matcher::Contains(keyword) => text.contains(keyword.as_str()),
+            Matcher::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+/// Keywords are folded too so they can be looked up in the folded text, regexes are used as is
+#[derive(Debug)]
+struct Pattern {
+    plain: Matcher,
+    folded: Matcher,
+    source: String,
+}
+
+impl Pattern {
+    fn is_match(&self, text: &str, view: View) -> bool {
+        match view {
+            View::Plain => self.plain.is_match(text),
+            View::Folded => self.folded.is_match(text),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum View {
+    Plain,
+    Folded,
+}
+
+impl TryFrom<PatternDefinition> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(definition: PatternDefinition) -> Result<Self, Self::Error> {
+        Ok(match definition {
+            PatternDefinition::Contains(keyword) => Pattern {
+                folded: Matcher::Contains(normalization::fold(&keyword)),
+                plain: Matcher::Contains(keyword.clone()),
+                source: keyword,
+            },
+            PatternDefinition::Word(word) => Pattern {
+                plain: Matcher::word(&word)?,
+                folded: Matcher::word(&normalization::fold(&word))?,
+                source: word,
+            },
+            PatternDefinition::Regex(regex) => Pattern {
+                plain: Matcher::Regex(Regex::new(&regex)?),
+                folded: Matcher::Regex(Regex::new(&regex)?),
+                source: format!("/{regex}/"),
+            },
+        })
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[derive(Debug)]
+pub struct KeywordRule {
+    pattern: Pattern,
+    unless: Vec<Pattern>,
+    pub weight: f64,
+    pub category: Option<String>,
+}
+
+impl KeywordRule {
+    /// An exclusion matching in either view vetoes the rule, folding must not be a way around it
+    pub fn is_match(&self, text: &NormalizedText) -> bool {
+        let views = [
+            (text.plain.as_str(), View::Plain),
+            (text.folded.as_str(), View::Folded),
+        ];
+        views
+            .iter()
+            .any(|&(text, view)| self.pattern.is_match(text, view))
+            && !views.iter().any(|&(text, view)| {
+                self.unless
+                    .iter()
+                    .any(|pattern| pattern.is_match(text, view))
+            })
+    }
+}
+
+impl TryFrom<KeywordRuleDefinition> for KeywordRule {
+    type Error = regex::Error;
+
+    fn try_from(definition: KeywordRuleDefinition) -> Result<Self, Self::Error> {
+        Ok(match definition {
+            KeywordRuleDefinition::Plain(keyword) => KeywordRule {
+                pattern: PatternDefinition::Contains(keyword).try_into()?,
+                unless: vec![],
+                weight: default_weight(),
+                category: None,
+            },
+            KeywordRuleDefinition::Extended {
+                pattern,
+                unless,
+                weight,
+                category,
+            } => KeywordRule {
+                pattern: pattern.try_into()?,
+                unless: unless
+                    .into_iter()
+                    .map(Pattern::try_from)
+                    .collect::<Result<_, _>>()?,
+                weight,
+                category,
+            },
+        })
+    }
+}
+
+impl Display for KeywordRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pattern)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(text: &str) -> NormalizedText {
+        NormalizedText::new(text)
+    }
+
+    fn parse(json: &str) -> Vec<KeywordRule> {
+        serde_json::from_str::<Vec<KeywordRuleDefinition>>(json)
+            .unwrap()
+            .into_iter()
+            .map(|definition| KeywordRule::try_from(definition).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_plain_keywords_are_compatible() {
+        let rules = parse(r#"["BIT.LY", "SANS PRISE DE TETE"]"#);
+        assert!(rules[0].is_match(&text("VA SUR BIT.LY/ABC")));
+        assert!(rules[1].is_match(&text("PLAN SANS PRISE DE TETE")));
+        assert!(!rules[1].is_match(&text("SANS PRISE")));
+    }
+
+    #[test]
+    fn test_word_boundary() {
+        let rules = parse(r#"[{ "word": "DISPO" }]"#);
+        assert!(rules[0].is_match(&text("JE SUIS DISPO CE SOIR")));
+        assert!(rules[0].is_match(&text("DISPO!")));
+        assert!(!rules[0].is_match(&text("LA SALLE EST DISPONIBLE")));
+    }
+
+    #[test]
+    fn test_regex_with_exclusion() {
+        let rules = parse(
+            r#"[{ "regex": "\\bESCORTE?S?\\b", "unless": [{ "contains": "ESCORTE POLICIERE" }] }]"#,
+        );
+        assert!(rules[0].is_match(&text("ESCORTE DISPONIBLE")));
+        assert!(rules[0].is_match(&text("ESCORTS")));
+        assert!(!rules[0].is_match(&text("ESCORTING")));
+        assert!(!rules[0].is_match(&text("SOUS ESCORTE POLICIERE")));
+    }
+
+    #[test]
+    fn test_exclusion_vetoes_both_views() {
+        let rules = parse(r#"[{ "contains": "CUL", "unless": [{ "regex": "CULOTTE" }] }]"#);
+        assert!(rules[0].is_match(&text("PLAN CUL")));
+        assert!(!rules[0].is_match(&text("une culotte rouge")));
+    }
+
+    #[test]
+    fn test_obfuscated_keywords() {
+        let rules = parse(r#"["ESCORT", { "word": "PLAN CUL" }, "+33"]"#);
+        assert!(rules[0].is_match(&text("3.S.C.0.R.T")));
+        assert!(rules[1].is_match(&text("pl4n cuuul")));
+        assert!(!rules[1].is_match(&text("plan culturel")));
+        assert!(rules[2].is_match(&text("+33612345678")));
+    }
+
+    #[test]
+    fn test_weight_and_category() {
+        let rules =
+            parse(r#"["BIT.LY", { "word": "PAYANT", "weight": 0.5, "category": "PRICE" }]"#);
+        assert_eq!(rules[0].weight, 1.);
+        assert_eq!(rules[0].category, None);
+        assert_eq!(rules[1].weight, 0.5);
+        assert_eq!(rules[1].category.as_deref(), Some("PRICE"));
+    }
+
+    #[test]
+    fn test_invalid_regex_is_rejected() {
+        let definition =
+            serde_json::from_str::<KeywordRuleDefinition>(r#"{ "regex": "(" }"#).unwrap();
+        assert!(KeywordRule::try_from(definition).is_err());
+    }
+}
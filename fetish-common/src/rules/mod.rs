@@ -0,0 +1,203 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{self, SystemTime},
+};
+
+use log::{debug, error, info};
+use serde::de::DeserializeOwned;
+use tokio::sync::broadcast;
+
+use crate::{detectors::VerdictKind, error::FetishResult};
+
+use self::{
+    domain_blocklist::DomainBlocklist,
+    keyword_rule::{KeywordRule, KeywordRuleDefinition},
+    sanction_template::LocalizedTemplates,
+    scoring::Scoring,
+};
+
+pub mod domain_blocklist;
+pub mod keyword_rule;
+pub mod sanction_template;
+pub mod scoring;
+
+const KEYWORDS_FILE: &str = "keywords.json";
+const MESSAGE_SANCTION: &str = "message";
+const MESSAGE_SANCTION_FILE: &str = "message.txt";
+const SCAM_ACCOUNT_SANCTION: &str = "scam_account";
+const SCAM_ACCOUNT_SANCTION_FILE: &str = "scam_account.txt";
+const SCORING_FILE: &str = "scoring.json";
+const DOMAINS_FILE: &str = "domains.json";
+const PROFILES_FILE: &str = "profiles.json";
+const WATCHED_FILES: [&str; 6] = [
+    KEYWORDS_FILE,
+    MESSAGE_SANCTION_FILE,
+    SCAM_ACCOUNT_SANCTION_FILE,
+    SCORING_FILE,
+    DOMAINS_FILE,
+    PROFILES_FILE,
+];
+const POLL_INTERVAL_SECONDS: u64 = 5;
+
+#[derive(Debug)]
+pub struct Rules {
+    pub keywords: Vec<KeywordRule>,
+    pub message_sanction: LocalizedTemplates,
+    pub scam_account_sanction: LocalizedTemplates,
+    pub scoring: Scoring,
+    pub domains: DomainBlocklist,
+    /// Matched against the display name and usernames of accounts, as whole words
+    pub profile_keywords: Vec<KeywordRule>,
+}
+
+impl Rules {
+    fn load(dir: &Path) -> FetishResult<Self> {
+        Ok(Self {
+            keywords: serde_json::from_str::<Vec<KeywordRuleDefinition>>(&fs::read_to_string(
+                dir.join(KEYWORDS_FILE),
+            )?)?
+            .into_iter()
+            .map(KeywordRule::try_from)
+            .collect::<Result<_, _>>()?,
+            message_sanction: LocalizedTemplates::load(dir, MESSAGE_SANCTION)?,
+            scam_account_sanction: LocalizedTemplates::load(dir, SCAM_ACCOUNT_SANCTION)?,
+            scoring: load_optional(&dir.join(SCORING_FILE))?,
+            domains: load_optional(&dir.join(DOMAINS_FILE))?,
+            profile_keywords: load_optional::<Vec<KeywordRuleDefinition>>(
+                &dir.join(PROFILES_FILE),
+            )?
+            .into_iter()
+            .map(|definition| KeywordRule::try_from(definition.whole_words()))
+            .collect::<Result<_, _>>()?,
+        })
+    }
+
+    pub fn sanction(&self, kind: VerdictKind) -> &LocalizedTemplates {
+        match kind {
+            VerdictKind::ScamAccount => &self.scam_account_sanction,
+            VerdictKind::ScamMessage => &self.message_sanction,
+        }
+    }
+}
+
+/// Optional files fall back to their default when missing
+fn load_optional<T: DeserializeOwned + Default>(path: &Path) -> FetishResult<T> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(serde_json::from_str(&content)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Keeps the rules of the resource directory in memory, readers always get a consistent snapshot
+pub struct RulesStore {
+    dir: PathBuf,
+    rules: RwLock<Arc<Rules>>,
+}
+
+impl RulesStore {
+    pub fn load(dir: impl Into<PathBuf>) -> FetishResult<Self> {
+        let dir = dir.into();
+        let rules = Rules::load(&dir)?;
+        debug!(
+            "Loaded {} keywords from '{}'",
+            rules.keywords.len(),
+            dir.display()
+        );
+        Ok(Self {
+            dir,
+            rules: RwLock::new(Arc::new(rules)),
+        })
+    }
+
+    pub fn get(&self) -> Arc<Rules> {
+        self.rules.read().unwrap().clone()
+    }
+
+    /// On failure the previously loaded rules are kept
+    pub fn reload(&self) -> FetishResult<()> {
+        let rules = Rules::load(&self.dir)?;
+        *self.rules.write().unwrap() = Arc::new(rules);
+        Ok(())
+    }
+
+    /// Language variants of the sanctions are watched too, adding one triggers a reload
+    fn last_modified(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let variants = [MESSAGE_SANCTION, SCAM_ACCOUNT_SANCTION]
+            .into_iter()
+            .flat_map(|name| sanction_template::variant_files(&self.dir, name).unwrap_or_default());
+        WATCHED_FILES
+            .iter()
+            .map(|file| self.dir.join(file))
+            .chain(variants)
+            .map(|path| {
+                let modified = fs::metadata(&path)
+                    .and_then(|metadata| metadata.modified())
+                    .ok();
+                (path, modified)
+            })
+            .collect()
+    }
+}
+
+pub async fn watch(store: Arc<RulesStore>, mut shutdown_rx: broadcast::Receiver<()>) {
+    info!("Watching rules in '{}'", store.dir.display());
+    let mut last_modified = store.last_modified();
+    let mut interval = tokio::time::interval(time::Duration::from_secs(POLL_INTERVAL_SECONDS));
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let modified = store.last_modified();
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+                match store.reload() {
+                    Ok(()) => info!("Rules reloaded"),
+                    Err(e) => error!("Failed to reload rules, keeping the previous ones: {e:#?}"),
+                }
+            }
+            _ = shutdown_rx.recv() => {
+                debug!("Shutting down rules watcher");
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_malformed_reload_keeps_rules() {
+        let dir = std::env::temp_dir().join(format!("fetish-rules-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join(KEYWORDS_FILE), r#"["ESCORT"]"#).unwrap();
+        fs::write(dir.join(MESSAGE_SANCTION_FILE), "message").unwrap();
+        fs::write(dir.join(SCAM_ACCOUNT_SANCTION_FILE), "scam account").unwrap();
+
+        let store = RulesStore::load(&dir).unwrap();
+        assert_eq!(store.get().keywords.len(), 1);
+
+        fs::write(dir.join(KEYWORDS_FILE), r#"["ESCORT", "DISPO"]"#).unwrap();
+        store.reload().unwrap();
+        assert_eq!(store.get().keywords.len(), 2);
+
+        fs::write(dir.join(KEYWORDS_FILE), r#"["ESCORT", "#).unwrap();
+        assert!(store.reload().is_err());
+        assert_eq!(store.get().keywords.len(), 2);
+        assert_eq!(
+            store
+                .get()
+                .sanction(VerdictKind::ScamMessage)
+                .get(None)
+                .source(),
+            "message"
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
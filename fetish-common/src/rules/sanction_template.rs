@@ -0,0 +1,266 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use tdlib::{
+    enums::TextEntityType,
+    types::{FormattedText, TextEntity},
+};
+
+use crate::error::FetishResult;
+
+/// What placeholders are replaced with
+pub struct TemplateVariables<'a> {
+    /// Display name of the sanctioned sender
+    pub sender: &'a str,
+    /// Reason of the highest scoring verdict
+    pub reason: &'a str,
+    /// Title of the chat
+    pub chat: &'a str,
+}
+
+impl TemplateVariables<'_> {
+    fn get(&self, name: &str) -> Option<&str> {
+        match name {
+            "sender" => Some(self.sender),
+            "reason" => Some(self.reason),
+            "chat" => Some(self.chat),
+            _ => None,
+        }
+    }
+}
+
+/// A sanction text with `{sender}`, `{reason}` and `{chat}` placeholders, `**bold**` and
+/// `__italic__` spans become entities
+///
+/// `{sender|This account}` renders the text after the bar when the value is empty.
+///
+/// Markers are only read from the template, never from the values of the placeholders.
+#[derive(Debug, Clone, Default)]
+pub struct SanctionTemplate {
+    source: String,
+}
+
+impl SanctionTemplate {
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    pub fn render(&self, variables: &TemplateVariables) -> FormattedText {
+        let mut text = String::new();
+        // Entities are measured in UTF-16 code units
+        let mut length = 0;
+        let mut entities = Vec::new();
+        let mut bold_start = None;
+        let mut italic_start = None;
+
+        let mut rest = self.source.as_str();
+        while let Some(c) = rest.chars().next() {
+            if let Some(after) = rest.strip_prefix("**") {
+                toggle_entity(&mut bold_start, TextEntityType::Bold, length, &mut entities);
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("__") {
+                toggle_entity(
+                    &mut italic_start,
+                    TextEntityType::Italic,
+                    length,
+                    &mut entities,
+                );
+                rest = after;
+                continue;
+            }
+            if let Some((value, after)) = rest
+                .strip_prefix('{')
+                .and_then(|after| after.split_once('}'))
+                .and_then(|(placeholder, after)| {
+                    let (name, fallback) = placeholder.split_once('|').unwrap_or((placeholder, ""));
+                    let value = variables.get(name)?;
+                    Some((if value.is_empty() { fallback } else { value }, after))
+                })
+            {
+                text.push_str(value);
+                length += value.encode_utf16().count() as i32;
+                rest = after;
+                continue;
+            }
+            text.push(c);
+            length += c.len_utf16() as i32;
+            rest = &rest[c.len_utf8()..];
+        }
+
+        // Unclosed spans run to the end of the text
+        toggle_entity(&mut bold_start, TextEntityType::Bold, length, &mut entities);
+        toggle_entity(
+            &mut italic_start,
+            TextEntityType::Italic,
+            length,
+            &mut entities,
+        );
+        entities.sort_by_key(|entity: &TextEntity| entity.offset);
+        FormattedText { text, entities }
+    }
+}
+
+fn toggle_entity(
+    start: &mut Option<i32>,
+    r#type: TextEntityType,
+    offset: i32,
+    entities: &mut Vec<TextEntity>,
+) {
+    match start.take() {
+        Some(start) if offset > start => entities.push(TextEntity {
+            offset: start,
+            length: offset - start,
+            r#type,
+        }),
+        Some(_) => {}
+        None => *start = Some(offset),
+    }
+}
+
+/// The variants of a sanction, `{name}.txt` is the default one and `{name}.{language_code}.txt`
+/// the one for a language
+#[derive(Debug, Default)]
+pub struct LocalizedTemplates {
+    default: SanctionTemplate,
+    by_language: HashMap<String, SanctionTemplate>,
+}
+
+impl LocalizedTemplates {
+    pub fn load(dir: &Path, name: &str) -> FetishResult<Self> {
+        let default = SanctionTemplate::new(fs::read_to_string(dir.join(format!("{name}.txt")))?);
+        let mut by_language = HashMap::new();
+        for path in variant_files(dir, name)? {
+            let Some(language_code) = path
+                .file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| language_code(file_name, name))
+            else {
+                continue;
+            };
+            by_language.insert(
+                language_code.to_lowercase(),
+                SanctionTemplate::new(fs::read_to_string(&path)?),
+            );
+        }
+        Ok(Self {
+            default,
+            by_language,
+        })
+    }
+
+    /// Regional codes such as `pt-br` fall back to their language, then to the default variant
+    pub fn get(&self, language_code: Option<&str>) -> &SanctionTemplate {
+        let Some(language_code) = language_code.map(str::to_lowercase) else {
+            return &self.default;
+        };
+        self.by_language
+            .get(&language_code)
+            .or_else(|| {
+                let (language, _) = language_code.split_once('-')?;
+                self.by_language.get(language)
+            })
+            .unwrap_or(&self.default)
+    }
+}
+
+/// Language variants of a sanction found in the directory
+pub fn variant_files(dir: &Path, name: &str) -> FetishResult<Vec<PathBuf>> {
+    let mut paths = fs::read_dir(dir)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|file_name| file_name.to_str())
+                .and_then(|file_name| language_code(file_name, name))
+                .is_some()
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    Ok(paths)
+}
+
+fn language_code<'a>(file_name: &'a str, name: &str) -> Option<&'a str> {
+    file_name
+        .strip_prefix(name)?
+        .strip_prefix('.')?
+        .strip_suffix(".txt")
+        .filter(|language_code| !language_code.is_empty() && !language_code.contains('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_entities_and_placeholders() {
+        let template =
+            SanctionTemplate::new("**Attention** {sender}, __{reason}__ in {chat} {other}");
+        let text = template.render(&TemplateVariables {
+            sender: "Ève 💋",
+            reason: "escort **offer**",
+            chat: "Paris",
+        });
+        assert_eq!(
+            text.text,
+            "Attention Ève 💋, escort **offer** in Paris {other}"
+        );
+        assert_eq!(
+            text.entities,
+            vec![
+                TextEntity {
+                    offset: 0,
+                    length: 9,
+                    r#type: TextEntityType::Bold,
+                },
+                TextEntity {
+                    offset: 18,
+                    length: 16,
+                    r#type: TextEntityType::Italic,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_placeholder_fallback() {
+        let template = SanctionTemplate::new("**{sender|This account}** is a scammer");
+        let variables = TemplateVariables {
+            sender: "",
+            reason: "",
+            chat: "",
+        };
+        let text = template.render(&variables);
+        assert_eq!(text.text, "This account is a scammer");
+        assert_eq!(text.entities[0].length, 12);
+        let text = template.render(&TemplateVariables {
+            sender: "Ève",
+            ..variables
+        });
+        assert_eq!(text.text, "Ève is a scammer");
+    }
+
+    #[test]
+    fn test_language_fallback() {
+        let templates = LocalizedTemplates {
+            default: SanctionTemplate::new("fr"),
+            by_language: HashMap::from([("en".to_owned(), SanctionTemplate::new("en"))]),
+        };
+        assert_eq!(templates.get(Some("en")).source(), "en");
+        assert_eq!(templates.get(Some("EN-gb")).source(), "en");
+        assert_eq!(templates.get(Some("de")).source(), "fr");
+        assert_eq!(templates.get(None).source(), "fr");
+        assert_eq!(language_code("message.pt-br.txt", "message"), Some("pt-br"));
+        assert_eq!(language_code("message.txt", "message"), None);
+    }
+}
@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use crate::detectors::{Assessment, Verdict, VerdictKind};
+
+/// Content of `scoring.json`
+///
+/// ```json
+/// {
+///     "threshold": 1.5,
+///     "chat_thresholds": { "-1001234567890": 2 },
+///     "chat_languages": { "-1001234567890": "en" },
+///     "combinations": [{ "categories": ["LINK", "PRICE"], "weight": 1 }],
+///     "promotion": { "offences": 3, "window_seconds": 604800 },
+///     "deduplication": { "window_seconds": 86400 },
+///     "forward_sources": { "scam_forwards": 3 }
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+pub struct Scoring {
+    #[serde(default = "default_threshold")]
+    pub threshold: f64,
+    #[serde(default)]
+    pub chat_thresholds: HashMap<i64, f64>,
+    /// Language of the sanction variant sent in a chat, others get the default variant
+    #[serde(default)]
+    pub chat_languages: HashMap<i64, String>,
+    #[serde(default)]
+    pub combinations: Vec<Combination>,
+    #[serde(default)]
+    pub promotion: Promotion,
+    #[serde(default)]
+    pub deduplication: Deduplication,
+    #[serde(default)]
+    pub forward_sources: ForwardSources,
+}
+
+/// Adds its weight when every category was hit by the message
+#[derive(Debug, Deserialize)]
+pub struct Combination {
+    pub categories: Vec<String>,
+    pub weight: f64,
+}
+
+/// A sender with this many scam messages across chats within the window is listed as scammer
+#[derive(Debug, Deserialize)]
+pub struct Promotion {
+    pub offences: usize,
+    pub window_seconds: i64,
+}
+
+impl Default for Promotion {
+    fn default() -> Self {
+        Self {
+            offences: 3,
+            window_seconds: 7 * 24 * 60 * 60,
+        }
+    }
+}
+
+/// A chat is sanctioned once per sender and per content within the window
+#[derive(Debug, Deserialize)]
+pub struct Deduplication {
+    pub window_seconds: i64,
+}
+
+impl Default for Deduplication {
+    fn default() -> Self {
+        Self {
+            window_seconds: 24 * 60 * 60,
+        }
+    }
+}
+
+/// A chat or channel is a bad source once this many of its forwards were scams
+#[derive(Debug, Deserialize)]
+pub struct ForwardSources {
+    pub scam_forwards: i64,
+}
+
+impl Default for ForwardSources {
+    fn default() -> Self {
+        Self { scam_forwards: 3 }
+    }
+}
+
+fn default_threshold() -> f64 {
+    1.
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Self {
+            threshold: default_threshold(),
+            chat_thresholds: HashMap::new(),
+            chat_languages: HashMap::new(),
+            combinations: vec![],
+            promotion: Promotion::default(),
+            deduplication: Deduplication::default(),
+            forward_sources: ForwardSources::default(),
+        }
+    }
+}
+
+impl Scoring {
+    pub fn threshold(&self, chat_id: i64) -> f64 {
+        self.chat_thresholds
+            .get(&chat_id)
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+
+    pub fn language(&self, chat_id: i64) -> Option<&str> {
+        self.chat_languages.get(&chat_id).map(String::as_str)
+    }
+
+    pub fn apply_combinations(&self, assessment: &mut Assessment) {
+        let categories = assessment.categories();
+        let verdicts = self
+            .combinations
+            .iter()
+            .filter(|combination| {
+                combination
+                    .categories
+                    .iter()
+                    .all(|category| categories.contains(category.as_str()))
+            })
+            .map(|combination| {
+                let mut verdict = Verdict::new(
+                    VerdictKind::ScamMessage,
+                    combination.weight,
+                    combination.categories.join(" + "),
+                );
+                verdict.source = "combination";
+                verdict
+            })
+            .collect::<Vec<_>>();
+        assessment.verdicts.extend(verdicts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn verdict(score: f64, category: &str) -> Verdict {
+        Verdict::new(VerdictKind::ScamMessage, score, category)
+            .with_categories(vec![category.to_owned()])
+    }
+
+    #[test]
+    fn test_combination_reaches_threshold() {
+        let scoring = serde_json::from_str::<Scoring>(
+            r#"{
+                "threshold": 2,
+                "chat_thresholds": { "-42": 3 },
+                "chat_languages": { "-42": "en" },
+                "combinations": [{ "categories": ["LINK", "PRICE"], "weight": 1 }]
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(scoring.threshold(-1), 2.);
+        assert_eq!(scoring.threshold(-42), 3.);
+        assert_eq!(scoring.language(-1), None);
+        assert_eq!(scoring.language(-42), Some("en"));
+
+        let mut assessment = Assessment {
+            verdicts: vec![verdict(0.5, "LINK")],
+        };
+        scoring.apply_combinations(&mut assessment);
+        assert_eq!(assessment.sanction_kind(scoring.threshold(-1)), None);
+
+        let mut assessment = Assessment {
+            verdicts: vec![verdict(0.5, "LINK"), verdict(0.5, "PRICE")],
+        };
+        scoring.apply_combinations(&mut assessment);
+        assert_eq!(assessment.score(), 2.);
+        assert_eq!(
+            assessment.sanction_kind(scoring.threshold(-1)),
+            Some(VerdictKind::ScamMessage)
+        );
+        assert_eq!(assessment.sanction_kind(scoring.threshold(-42)), None);
+    }
+}
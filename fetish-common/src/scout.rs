@@ -6,7 +6,7 @@ use std::{
 
 use crate::{
     database::Database,
-    error::FetishResult,
+    error::{retry_after, FetishResult},
     location::Location,
     models::{
         basic_group_wrapper::BasicGroupWrapper, chat_wrapper::ChatWrapper,
@@ -15,7 +15,6 @@ use crate::{
 };
 use chrono::Utc;
 use log::{debug, info, warn};
-use regex::Regex;
 use tdlib::{
     enums, functions,
     types::{Chat, ChatMemberStatusRestricted, ChatPermissions},
@@ -93,8 +92,10 @@ impl ScoutedLocation {
             return Ok(None);
         };
         let last_scouted_chat = last_scouted_chat.clone();
-        scouted_chats
-            .retain(|scouted_chat| scouted_chat.scouted_at == last_scouted_chat.scouted_at);
+        scouted_chats.retain(|scouted_chat| {
+            scouted_chat.scouted_at == last_scouted_chat.scouted_at
+                && scouted_chat.lost_at.is_none()
+        });
         Ok(Some(ScoutedLocation {
             conn,
             location: last_scouted_chat.location,
@@ -149,36 +150,19 @@ impl ScoutedLocation {
                 continue;
             };
 
-            let status = match &nearby_chat.r#type {
-                enums::ChatType::Supergroup(supergroup) => {
-                    let Some(supergroup) =
-                        conn.load::<SupergroupWrapper>(supergroup.supergroup_id)?
-                    else {
-                        warn!(
-                            "Supergroup not found in database: {}",
-                            supergroup.supergroup_id
-                        );
-                        continue;
-                    };
-                    supergroup.status.clone()
-                }
-                enums::ChatType::BasicGroup(basic_group) => {
-                    let Some(basic_group) =
-                        conn.load::<BasicGroupWrapper>(basic_group.basic_group_id)?
-                    else {
-                        warn!(
-                            "Basic group not found in database: {}",
-                            basic_group.basic_group_id
-                        );
-                        continue;
-                    };
-                    basic_group.status.clone()
-                }
-                _ => {
-                    continue;
-                }
+            let Some(status) = member_status(&conn, &nearby_chat)? else {
+                warn!("Group of chat not found in database: {}", nearby_chat.id);
+                continue;
             };
 
+            if conn
+                .load::<ScoutedChat>(nearby_chat.id)?
+                .is_some_and(|scouted_chat| scouted_chat.lost_at.is_some())
+            {
+                info!("Chat '{}' was lost, not joining again", nearby_chat.title);
+                continue;
+            }
+
             if !can_join_chat(&nearby_chat, &status) {
                 info!("Chat '{}' is not suitable", nearby_chat.title);
                 continue;
@@ -202,6 +186,7 @@ impl ScoutedLocation {
                 location,
                 scouted_at,
                 joined_at,
+                lost_at: None,
             })?;
         }
 
@@ -251,12 +236,20 @@ impl ScoutedLocation {
         if let Some((chat_id, joined_at)) = self.get_next_unjoined_chat() {
             join_chat(*chat_id, client_id).await;
             *joined_at = Some(Utc::now().timestamp());
-            conn.lock().unwrap().save(&ScoutedChat {
-                chat_id: *chat_id,
-                location,
-                scouted_at,
-                joined_at: *joined_at,
-            })?;
+            {
+                let conn = conn.lock().unwrap();
+                // The update dispatcher clears lost_at once our new status is confirmed
+                let lost_at = conn
+                    .load::<ScoutedChat>(*chat_id)?
+                    .and_then(|scouted_chat| scouted_chat.lost_at);
+                conn.save(&ScoutedChat {
+                    chat_id: *chat_id,
+                    location,
+                    scouted_at,
+                    joined_at: *joined_at,
+                    lost_at,
+                })?;
+            }
             sleep(CHAT_JOIN_COOLDOW_SECONDS as u64).await;
             Ok(Some(()))
         } else {
@@ -336,6 +329,22 @@ fn filter_locations(
         .collect::<Vec<Location>>())
 }
 
+/// Our status in a group as last stored, `None` for other chats and unknown groups
+pub fn member_status(
+    conn: &Database,
+    chat: &Chat,
+) -> FetishResult<Option<enums::ChatMemberStatus>> {
+    Ok(match &chat.r#type {
+        enums::ChatType::Supergroup(supergroup) => conn
+            .load::<SupergroupWrapper>(supergroup.supergroup_id)?
+            .map(|supergroup| supergroup.status.clone()),
+        enums::ChatType::BasicGroup(basic_group) => conn
+            .load::<BasicGroupWrapper>(basic_group.basic_group_id)?
+            .map(|basic_group| basic_group.status.clone()),
+        _ => None,
+    })
+}
+
 fn can_join_chat(chat: &Chat, status: &enums::ChatMemberStatus) -> bool {
     chat.permissions.can_send_basic_messages
         && match status {
@@ -374,17 +383,13 @@ async fn join_chat(chat_id: i64, client_id: i32) {
     info!("Joining chat '{chat_id}'");
     while let Err(e) = functions::join_chat(chat_id, client_id).await {
         warn!("Error joining chat '{chat_id}': {e:#?}");
-        if let tdlib::types::Error { code: 429, message } = e {
-            // The message is "Too Many Requests: retry after {seconds}"
-            if let Some(c) = Regex::new(r"after (\d+)").unwrap().captures(&message) {
-                let seconds = c.get(1).unwrap().as_str().parse::<u64>().unwrap();
-                warn!("Too many requests, retry in {seconds} seconds");
-                let _ = fs::write(
-                    PUNISHED_FILE_PATH,
-                    format!("{}", Utc::now().timestamp() + seconds as i64),
-                );
-                sleep(seconds).await;
-            }
+        if let Some(seconds) = retry_after(&e) {
+            warn!("Too many requests, retry in {seconds} seconds");
+            let _ = fs::write(
+                PUNISHED_FILE_PATH,
+                format!("{}", Utc::now().timestamp() + seconds as i64),
+            );
+            sleep(seconds).await;
         }
     }
 }
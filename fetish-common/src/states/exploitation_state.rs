@@ -1,48 +1,219 @@
-use std::{
-    fs,
-    sync::{Arc, Mutex},
-};
+use std::sync::{Arc, Mutex};
 
 use async_trait::async_trait;
-use log::{debug, error, info, trace};
+use chrono::Utc;
+use log::{debug, error, info};
 use tdlib::{
-    enums::{InputMessageContent, MessageContent, MessageSender, User},
+    enums::{InputMessageContent, MessageSender, User},
     functions,
-    types::{FormattedText, InputMessageText, Message, MessageSenderUser},
+    types::{FormattedText, InputMessageText, Message, MessageSenderChat, MessageSenderUser},
 };
-use unidecode::unidecode;
+use tokio::sync::mpsc;
 
 use crate::{
     application::ApplicationData,
     database::Database,
-    error::{FetishError, FetishResult},
+    detectors::{
+        forward_detector::forward_origin, message_text, Assessment, DetectorPipeline, VerdictKind,
+    },
+    error::FetishResult,
     location::Location,
-    models::scammer::Scammer,
+    media::{self, CachePolicy},
+    models::{
+        chat_policy::{ChatPolicy, Policy},
+        chat_wrapper::ChatWrapper,
+        forward_source::ForwardSource,
+        message_assessment::MessageAssessment,
+        queued_action::{OutgoingSanction, QueuedAction},
+        sanction::{Sanction, SanctionStatus},
+        scammer::{Scammer, ScammerReason},
+        scammer_evidence::ScammerEvidence,
+        user_wrapper::UserWrapper,
+    },
+    normalization::NormalizedText,
+    rules::{
+        self,
+        sanction_template::{LocalizedTemplates, TemplateVariables},
+        scoring::{Deduplication, ForwardSources, Promotion},
+        RulesStore,
+    },
     scout,
 };
 
 use super::ApplicationState;
 
-pub type SendMessageData = (Message, InputMessageContent, i32);
+/// Longest text TDLib accepts along a report
+const REPORT_TEXT_MAX_CHARS: usize = 1024;
 
 pub struct ExploitationState {
     locations: Vec<Location>,
+    pipeline: DetectorPipeline,
+    rules: Arc<RulesStore>,
+    cache_policy: CachePolicy,
+    dry_run: bool,
 }
 
 impl ExploitationState {
-    pub fn new(locations: Vec<Location>) -> Self {
-        Self { locations }
+    pub fn new(
+        locations: Vec<Location>,
+        pipeline: DetectorPipeline,
+        rules: Arc<RulesStore>,
+        cache_policy: CachePolicy,
+    ) -> Self {
+        Self {
+            locations,
+            pipeline,
+            rules,
+            cache_policy,
+            dry_run: false,
+        }
+    }
+
+    /// Sanctions are recorded and logged but never sent
+    pub fn set_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Assesses a message then records its sanction, which is queued for the message sender
+    /// when the chat policy asks for one
+    async fn handle_message(
+        &self,
+        message: Message,
+        db: Arc<Mutex<Database>>,
+        client_id: i32,
+        me_id: i64,
+        message_to_send_tx: &mpsc::Sender<QueuedAction>,
+    ) -> FetishResult<()> {
+        // Skip messages from private chats
+        if message.chat_id >= 0 {
+            return Ok(());
+        }
+
+        // Skip messages from me
+        if let MessageSender::User(MessageSenderUser { user_id }) = message.sender_id {
+            if user_id == me_id {
+                return Ok(());
+            }
+        }
+
+        let policy = db
+            .lock()
+            .unwrap()
+            .load::<ChatPolicy>(message.chat_id)?
+            .map(|chat_policy| chat_policy.policy)
+            .unwrap_or_default();
+        if policy == Policy::Ignore {
+            return Ok(());
+        }
+
+        let mut assessment = self.pipeline.run(&message, db.clone(), client_id).await?;
+        if assessment.verdicts.is_empty() {
+            return Ok(());
+        }
+
+        let rules = self.rules.get();
+        rules.scoring.apply_combinations(&mut assessment);
+        let threshold = rules.scoring.threshold(message.chat_id);
+        let sanction_kind = assessment.sanction_kind(threshold);
+        info!(
+            "Message {} scored {assessment} (threshold {threshold})",
+            message.id
+        );
+        db.lock().unwrap().save(&MessageAssessment {
+            message_id: message.id,
+            chat_id: message.chat_id,
+            score: assessment.score(),
+            threshold,
+            is_scam: sanction_kind.is_some(),
+            breakdown: serde_json::to_string(&assessment.verdicts)?,
+            assessed_at: Utc::now().timestamp(),
+            dry_run: self.dry_run,
+        })?;
+        // Shadow mode verdicts must not feed the lists that later sanctions rely on
+        if !self.dry_run {
+            record_scammer_hit(&db.lock().unwrap(), &message, &assessment)?;
+            if sanction_kind == Some(VerdictKind::ScamMessage) {
+                promote_repeat_offender(&db.lock().unwrap(), &message, &rules.scoring.promotion)?;
+            }
+            if sanction_kind.is_some() {
+                record_forward_source(
+                    &db.lock().unwrap(),
+                    &message,
+                    &rules.scoring.forward_sources,
+                )?;
+            }
+        }
+
+        if let Some(kind) = sanction_kind {
+            match policy {
+                Policy::Warn | Policy::Report => {
+                    let outgoing = if policy == Policy::Warn {
+                        let text = render_sanction(
+                            &db.lock().unwrap(),
+                            &message,
+                            &assessment,
+                            rules.sanction(kind),
+                            rules.scoring.language(message.chat_id),
+                        )?;
+                        OutgoingSanction::Reply(reply_content(text))
+                    } else {
+                        OutgoingSanction::Report(report_text(kind, &assessment))
+                    };
+                    let must_send = record_sanction(
+                        &db.lock().unwrap(),
+                        &message,
+                        kind,
+                        &assessment,
+                        &outgoing,
+                        &rules.scoring.deduplication,
+                        self.dry_run,
+                    )?;
+                    if must_send {
+                        let action = QueuedAction::new(message, outgoing, Utc::now().timestamp());
+                        db.lock().unwrap().save(&action)?;
+                        // A full buffer is caught up from the database by the message sender
+                        if message_to_send_tx.try_send(action).is_err() {
+                            debug!("Message sender buffer is full");
+                        }
+                    }
+                }
+                Policy::Observe | Policy::Ignore => debug!(
+                    "Chat {} is observed, message {} is only recorded",
+                    message.chat_id, message.id
+                ),
+            }
+        }
+        Ok(())
     }
 }
 
 #[async_trait]
 impl ApplicationState for ExploitationState {
     async fn run(&self, mut app_data: ApplicationData) -> FetishResult<ApplicationData> {
-        let (message_to_send_tx, message_to_send_rx) = tokio::sync::mpsc::unbounded_channel();
+        let (message_to_send_tx, message_to_send_rx) = mpsc::channel(message_sender::BUFFER_SIZE);
         let shutdown_rx = app_data.shutdown_rx.resubscribe();
         debug!("Starting message sender");
-        let message_sender_handle =
-            tokio::spawn(message_sender::run(shutdown_rx, message_to_send_rx));
+        let message_sender_handle = tokio::spawn(message_sender::run(
+            shutdown_rx,
+            message_to_send_rx,
+            app_data.conn.clone(),
+            app_data.client_id,
+        ));
+
+        debug!("Starting rules watcher");
+        let rules_watcher_handle = tokio::spawn(rules::watch(
+            self.rules.clone(),
+            app_data.shutdown_rx.resubscribe(),
+        ));
+
+        debug!("Starting cache cleaner");
+        let cache_cleaner_handle = tokio::spawn(media::clean_cache(
+            app_data.conn.clone(),
+            self.cache_policy.clone(),
+            app_data.client_id,
+            app_data.shutdown_rx.resubscribe(),
+        ));
 
         info!("Start listening for messages");
         let User::User(me) = functions::get_me(app_data.client_id).await.unwrap();
@@ -58,37 +229,11 @@ impl ApplicationState for ExploitationState {
         loop {
             tokio::select! {
                 Some(message) = app_data.message_rx.recv() => {
-                    let failsafe = {
-                        // Skip messages from private chats
-                        if message.chat_id >= 0 {
-                            continue;
-                        }
-
-                        if let Some((user_id, is_scammer)) = is_scammer_account(app_data.conn.clone(), &message)? {
-                            // Skip messages from me
-                            if user_id == me.id {
-                                continue;
-                            }
-
-                            if is_scammer {
-                                info!("Scammer detected: {user_id}");
-                                let sanction = fs::read_to_string("res/scam_account.txt")?;
-                                send_sanction(&message_to_send_tx, message, sanction, app_data.client_id).await?;
-                                continue;
-                            }
-                        }
-
-                        if is_scam_message(&message)? {
-                            info!("Scam message detected");
-                            let sanction = fs::read_to_string("res/message.txt")?;
-                            send_sanction(&message_to_send_tx, message, sanction, app_data.client_id).await?;
-                            continue;
-                        }
-
-                        Ok(())
-                    } as FetishResult<()>;
-
-                    if let Err(e) = failsafe {
+                    let conn = app_data.conn.clone();
+                    if let Err(e) = self
+                        .handle_message(message, conn, app_data.client_id, me.id, &message_to_send_tx)
+                        .await
+                    {
                         error!("Message handling error: {e:#?}");
                     }
                 },
@@ -101,6 +246,10 @@ impl ApplicationState for ExploitationState {
 
         debug!("Waiting for message sender to finish");
         message_sender_handle.await?;
+        debug!("Waiting for rules watcher to finish");
+        rules_watcher_handle.await?;
+        debug!("Waiting for cache cleaner to finish");
+        cache_cleaner_handle.await?;
         debug!("Waiting for scout to finish");
         scout_handle.await??;
         info!("Stop listening for messages");
@@ -108,109 +257,868 @@ impl ApplicationState for ExploitationState {
     }
 }
 
-fn is_scammer_account(
-    db: Arc<Mutex<Database>>,
+/// Keeps the metadata of listed scammers up to date, senders only flagged by Telegram are listed
+/// on their first hit
+fn record_scammer_hit(
+    db: &Database,
     message: &Message,
-) -> FetishResult<Option<(i64, bool)>> {
-    Ok(match message.sender_id {
-        MessageSender::User(MessageSenderUser { user_id }) => Some((
+    assessment: &Assessment,
+) -> FetishResult<()> {
+    let MessageSender::User(MessageSenderUser { user_id }) = message.sender_id else {
+        return Ok(());
+    };
+    let Some(verdict) = assessment
+        .verdicts
+        .iter()
+        .find(|verdict| verdict.kind == VerdictKind::ScamAccount)
+    else {
+        return Ok(());
+    };
+
+    let now = Utc::now().timestamp();
+    let mut scammer = match db.load::<Scammer>(user_id)? {
+        Some(scammer) => scammer,
+        None if verdict.source == "telegram_flag" => {
+            Scammer::new(user_id, ScammerReason::TelegramFlag, "telegram", now)
+        }
+        None => return Ok(()),
+    };
+    // Edited messages are assessed again, a message only counts once
+    if db
+        .load::<ScammerEvidence>((user_id, message.chat_id, message.id))?
+        .is_some()
+    {
+        return Ok(());
+    }
+    scammer.last_seen = now;
+    scammer.hit_count += 1;
+    db.save(&scammer)?;
+    db.save(&ScammerEvidence {
+        user_id,
+        message_id: message.id,
+        chat_id: message.chat_id,
+        added_at: now,
+    })?;
+    Ok(())
+}
+
+/// Lists a sender as scammer once it reached the promotion offences, demotion stays manual
+fn promote_repeat_offender(
+    db: &Database,
+    message: &Message,
+    promotion: &Promotion,
+) -> FetishResult<()> {
+    let MessageSender::User(MessageSenderUser { user_id }) = message.sender_id else {
+        return Ok(());
+    };
+    if db.load::<Scammer>(user_id)?.is_some() {
+        return Ok(());
+    }
+
+    let now = Utc::now().timestamp();
+    let offences = MessageAssessment::select_scams_by_sender(
+        &message.sender_id,
+        now - promotion.window_seconds,
+        db.connection(),
+    )?;
+    if offences.len() < promotion.offences {
+        return Ok(());
+    }
+
+    info!(
+        "Promoting user {user_id} to scammer after {} scam messages",
+        offences.len()
+    );
+    let mut scammer = Scammer::new(user_id, ScammerReason::RepeatOffender, "promotion", now);
+    scammer.first_seen = offences
+        .iter()
+        .map(|offence| offence.assessed_at)
+        .min()
+        .unwrap_or(now);
+    scammer.hit_count = offences.len() as i64;
+    db.save(&scammer)?;
+    for offence in offences {
+        db.save(&ScammerEvidence {
             user_id,
-            db.lock().unwrap().load::<Scammer>(user_id)?.is_some(),
-        )),
-        _ => None,
-    })
+            message_id: offence.message_id,
+            chat_id: offence.chat_id,
+            added_at: now,
+        })?;
+    }
+    Ok(())
 }
 
-fn is_scam_message(message: &Message) -> FetishResult<bool> {
-    let text = match &message.content {
-        MessageContent::MessageText(message_text) => message_text.text.text.clone(),
-        MessageContent::MessagePhoto(message_photo) => message_photo.caption.text.clone(),
-        MessageContent::MessageVideo(message_video) => message_video.caption.text.clone(),
-        MessageContent::MessageAnimation(message_animation) => {
-            message_animation.caption.text.clone()
+/// Counts the scam forwards of the chat or channel a sanctioned message was forwarded from, the
+/// forward detector flags it once it reached the configured count
+fn record_forward_source(
+    db: &Database,
+    message: &Message,
+    forward_sources: &ForwardSources,
+) -> FetishResult<()> {
+    let Some(MessageSender::Chat(MessageSenderChat { chat_id })) = forward_origin(message) else {
+        return Ok(());
+    };
+
+    let now = Utc::now().timestamp();
+    let mut source = db
+        .load::<ForwardSource>(chat_id)?
+        .unwrap_or_else(|| ForwardSource::new(chat_id, now));
+    source.scam_forwards += 1;
+    source.last_seen = now;
+    db.save(&source)?;
+    if source.scam_forwards == forward_sources.scam_forwards {
+        info!(
+            "Chat {chat_id} is now a bad forward source after {} scam forwards",
+            source.scam_forwards
+        );
+    }
+    Ok(())
+}
+
+/// Records the sanction of a message, returns whether it has to be sent
+///
+/// Nothing is sent on dry runs, for messages already sanctioned before an edit, nor when the
+/// sender or the same content was already sanctioned in the chat within the deduplication window
+fn record_sanction(
+    db: &Database,
+    message: &Message,
+    kind: VerdictKind,
+    assessment: &Assessment,
+    outgoing: &OutgoingSanction,
+    deduplication: &Deduplication,
+    dry_run: bool,
+) -> FetishResult<bool> {
+    // Edited messages are assessed again, their first sanction stands
+    if db
+        .load::<Sanction>((message.chat_id, message.id))?
+        .is_some()
+    {
+        debug!(
+            "Message {} of chat {} is already sanctioned",
+            message.id, message.chat_id
+        );
+        return Ok(false);
+    }
+
+    let (method, template) = (outgoing.method(), outgoing.template());
+    let now = Utc::now().timestamp();
+    let sender_id = match message.sender_id {
+        MessageSender::User(MessageSenderUser { user_id }) => user_id,
+        MessageSender::Chat(MessageSenderChat { chat_id }) => chat_id,
+    };
+    let content_key = message_text(message)
+        .map(|text| NormalizedText::new(&text.text).folded)
+        .filter(|folded| !folded.trim().is_empty());
+    let duplicate = Sanction::select_recent_duplicate(
+        message.chat_id,
+        sender_id,
+        content_key.as_deref(),
+        now - deduplication.window_seconds,
+        dry_run,
+        db.connection(),
+    )?;
+
+    let (status, error) = match duplicate {
+        Some(duplicate) => {
+            let error = format!(
+                "duplicate of the sanction on message {}",
+                duplicate.message_id
+            );
+            info!(
+                "Message {} of chat {} is not sanctioned, {error}",
+                message.id, message.chat_id
+            );
+            (SanctionStatus::Skipped, Some(error))
         }
-        _ => {
-            trace!("{:#?}", message.content);
-            return Ok(false);
+        None if dry_run => {
+            info!(
+                "Dry run, message {} of chat {} would get a {method}: {template}",
+                message.id, message.chat_id
+            );
+            (SanctionStatus::Skipped, None)
         }
+        None => (SanctionStatus::Pending, None),
     };
-    info!("{}: {text}", message.chat_id);
-    let text = unidecode(text.to_uppercase().as_str());
-    let is_scam =
-        serde_json::from_str::<Vec<String>>(fs::read_to_string("res/keywords.json")?.as_str())?
-            .into_iter()
-            .any(|keyword| text.contains(&keyword));
-    Ok(is_scam)
+    db.save(&Sanction {
+        chat_id: message.chat_id,
+        message_id: message.id,
+        kind: kind.to_string(),
+        reason: assessment.to_string(),
+        template,
+        is_dry_run: dry_run,
+        created_at: now,
+        status,
+        error,
+        sent_message_id: None,
+        sent_at: None,
+        sender_id: Some(sender_id),
+        content_key,
+        method,
+    })?;
+    Ok(status == SanctionStatus::Pending)
 }
 
-async fn send_sanction(
-    message_to_send_tx: &tokio::sync::mpsc::UnboundedSender<SendMessageData>,
-    message: Message,
-    sanction: String,
-    client_id: i32,
-) -> FetishResult<()> {
-    message_to_send_tx
-        .send((
-            message,
-            InputMessageContent::InputMessageText(InputMessageText {
-                text: FormattedText {
-                    text: sanction,
-                    entities: vec![],
-                },
-                disable_web_page_preview: true,
-                clear_draft: false,
-            }),
-            client_id,
-        ))
-        .map_err(|_| FetishError::MessageHandle)?;
-    Ok(())
+/// Picks the variant of the chat language and fills it with what is known of the message
+fn render_sanction(
+    db: &Database,
+    message: &Message,
+    assessment: &Assessment,
+    templates: &LocalizedTemplates,
+    language_code: Option<&str>,
+) -> FetishResult<FormattedText> {
+    let chat_title = |chat_id| -> FetishResult<String> {
+        Ok(db
+            .load::<ChatWrapper>(chat_id)?
+            .map(|chat| chat.title.clone())
+            .unwrap_or_default())
+    };
+    // Unknown senders render empty, templates give a fallback for them
+    let sender = match message.sender_id {
+        MessageSender::User(MessageSenderUser { user_id }) => db
+            .load::<UserWrapper>(user_id)?
+            .map(|user| {
+                format!("{} {}", user.first_name, user.last_name)
+                    .trim()
+                    .to_owned()
+            })
+            .unwrap_or_default(),
+        MessageSender::Chat(MessageSenderChat { chat_id }) => chat_title(chat_id)?,
+    };
+    let reason = assessment
+        .verdicts
+        .iter()
+        .max_by(|a, b| a.score.total_cmp(&b.score))
+        .map(|verdict| verdict.reason.as_str())
+        .unwrap_or_default();
+    Ok(templates.get(language_code).render(&TemplateVariables {
+        sender: &sender,
+        reason,
+        chat: &chat_title(message.chat_id)?,
+    }))
+}
+
+fn reply_content(text: FormattedText) -> InputMessageContent {
+    InputMessageContent::InputMessageText(InputMessageText {
+        text,
+        disable_web_page_preview: true,
+        clear_draft: false,
+    })
+}
+
+/// Report details for Telegram moderators, the verdicts behind the sanction
+fn report_text(kind: VerdictKind, assessment: &Assessment) -> String {
+    format!("{kind}: {assessment}")
+        .chars()
+        .take(REPORT_TEXT_MAX_CHARS)
+        .collect()
 }
 
 mod message_sender {
-    use std::time;
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+        time,
+    };
 
-    use log::{debug, error, info};
+    use chrono::Utc;
+    use log::{debug, error, info, warn};
     use rand::Rng;
-    use tdlib::{enums::MessageReplyTo, types::MessageReplyToMessage};
+    use tdlib::{
+        enums::{self, ChatMemberStatus, ChatType, MessageReplyTo, MessageSender, ReportReason},
+        functions,
+        types::{
+            ChatMemberStatusRestricted, ChatPermissions, Error, Message, MessageReplyToMessage,
+            MessageSenderChat, MessageSenderUser,
+        },
+    };
     use tokio::sync::{broadcast, mpsc};
 
-    use super::SendMessageData;
+    use crate::{
+        database::Database,
+        error::{retry_after, FetishResult},
+        models::{
+            chat_wrapper::ChatWrapper,
+            queued_action::{OutgoingSanction, QueuedAction},
+            sanction::{Sanction, SanctionStatus},
+            supergroup_wrapper::SupergroupWrapper,
+        },
+        scout,
+    };
+
+    /// Actions held in memory, the others wait in the database
+    pub const BUFFER_SIZE: usize = 64;
+    /// Random delay between two actions, in milliseconds
+    const GLOBAL_INTERVAL: (u64, u64) = (3000, 6000);
+    const CHAT_INTERVAL_SECONDS: i64 = 60;
+    /// How often the queue is caught up from the database
+    const REFILL_INTERVAL_SECONDS: u64 = 30;
+    /// Retry after errors only, other errors fail the sanction at once
+    const MAX_ATTEMPTS: i64 = 5;
+
+    /// Global pace of the sender, actions of a chat are spaced further apart
+    #[derive(Default)]
+    struct RateLimiter {
+        next_at: Option<tokio::time::Instant>,
+        last_sent_in_chat: HashMap<i64, i64>,
+    }
+
+    impl RateLimiter {
+        fn chat_ready_at(&self, chat_id: i64) -> i64 {
+            self.last_sent_in_chat
+                .get(&chat_id)
+                .map_or(0, |sent_at| sent_at + CHAT_INTERVAL_SECONDS)
+        }
+
+        fn record(&mut self, chat_id: i64) {
+            let (min, max) = GLOBAL_INTERVAL;
+            let interval = rand::thread_rng().gen_range(min..max);
+            self.next_at =
+                Some(tokio::time::Instant::now() + time::Duration::from_millis(interval));
+            self.last_sent_in_chat
+                .insert(chat_id, Utc::now().timestamp());
+        }
+
+        /// Flood errors hold every action, not only those of the chat
+        fn pause(&mut self, seconds: u64) {
+            let until = tokio::time::Instant::now() + time::Duration::from_secs(seconds);
+            self.next_at = Some(self.next_at.map_or(until, |next_at| next_at.max(until)));
+        }
+    }
 
     pub async fn run(
         mut shutdown_rx: broadcast::Receiver<()>,
-        mut message_to_send_rx: mpsc::UnboundedReceiver<SendMessageData>,
+        mut message_to_send_rx: mpsc::Receiver<QueuedAction>,
+        db: Arc<Mutex<Database>>,
+        client_id: i32,
     ) {
         info!("Starting message sender");
+        let mut rate_limiter = RateLimiter::default();
+        let mut refill = tokio::time::interval(time::Duration::from_secs(REFILL_INTERVAL_SECONDS));
         loop {
-            tokio::select! {
-                Some(send_message_data) = message_to_send_rx.recv() => {
-                    let (message, input_message, client_id) = send_message_data;
-                    info!("Sending message");
-                    let (min, max) = (3000, 6000);
-                    let waiting_time = (rand::thread_rng().gen::<f64>() * (max - min) as f64) as u64 + min;
-                    info!("Waiting for {waiting_time} ms");
-                    tokio::time::sleep(time::Duration::from_millis(waiting_time)).await;
-                    if let Err(e) = tdlib::functions::send_message(
-                        message.chat_id,
-                        message.message_thread_id,
-                        Some(MessageReplyTo::Message(MessageReplyToMessage {
-                            chat_id: message.chat_id,
-                            message_id: message.id,
-                        })),
-                        None,
-                        input_message,
-                        client_id,
-                    )
-                    .await
-                    {
-                        error!("Failed to send message: {e:#?}");
+            let actions = tokio::select! {
+                Some(action) = message_to_send_rx.recv() => vec![action],
+                _ = refill.tick() => {
+                    match QueuedAction::select_due(Utc::now().timestamp(), BUFFER_SIZE, db.lock().unwrap().connection()) {
+                        Ok(actions) => actions,
+                        Err(e) => {
+                            error!("Failed to load queued actions: {e:#?}");
+                            continue;
+                        }
                     }
                 }
                 _ = shutdown_rx.recv() => {
                     debug!("Shutting down message sender");
                     break;
                 }
+            };
+            for action in actions {
+                // Only the waiting is interrupted, queued actions are resumed on the next run
+                if let Some(next_at) = rate_limiter.next_at {
+                    tokio::select! {
+                        _ = tokio::time::sleep_until(next_at) => {}
+                        _ = shutdown_rx.recv() => {
+                            debug!("Shutting down message sender");
+                            return;
+                        }
+                    }
+                }
+                if let Err(e) = process(&db, &mut rate_limiter, action, client_id).await {
+                    error!("Failed to process queued action: {e:#?}");
+                }
+            }
+        }
+    }
+
+    async fn process(
+        db: &Arc<Mutex<Database>>,
+        rate_limiter: &mut RateLimiter,
+        action: QueuedAction,
+        client_id: i32,
+    ) -> FetishResult<()> {
+        // The same action can come from both the buffer and the database
+        let Some(mut action) = db
+            .lock()
+            .unwrap()
+            .load::<QueuedAction>((action.chat_id, action.message_id))?
+        else {
+            return Ok(());
+        };
+        let now = Utc::now().timestamp();
+        if action.not_before > now {
+            return Ok(());
+        }
+        let chat_ready_at = rate_limiter.chat_ready_at(action.chat_id);
+        if chat_ready_at > now {
+            debug!(
+                "Chat {} rate limited, action on message {} postponed",
+                action.chat_id, action.message_id
+            );
+            action.not_before = chat_ready_at;
+            return db.lock().unwrap().save(&action);
+        }
+
+        if let OutgoingSanction::Reply(_) = action.action {
+            let check = check_send(&db.lock().unwrap(), action.chat_id)?;
+            match check {
+                SendCheck::Allowed => {}
+                SendCheck::SlowMode(supergroup_id) => {
+                    match functions::get_supergroup_full_info(supergroup_id, client_id).await {
+                        Ok(enums::SupergroupFullInfo::SupergroupFullInfo(full_info))
+                            if full_info.slow_mode_delay_expires_in > 0. =>
+                        {
+                            let seconds = full_info.slow_mode_delay_expires_in.ceil() as i64;
+                            debug!(
+                                "Slow mode in chat {}, action on message {} postponed by {seconds} seconds",
+                                action.chat_id, action.message_id
+                            );
+                            action.not_before = now + seconds;
+                            return db.lock().unwrap().save(&action);
+                        }
+                        Ok(_) => {}
+                        // Counts as an attempt at sending, the delay can't be known otherwise
+                        Err(e) => {
+                            return settle(&db.lock().unwrap(), rate_limiter, action, Err(e));
+                        }
+                    }
+                }
+                SendCheck::Drop(reason) => {
+                    info!(
+                        "Dropping the reply to message {} of chat {}: {reason}",
+                        action.message_id, action.chat_id
+                    );
+                    let db = db.lock().unwrap();
+                    record_skipped(&db, &action.message, reason)?;
+                    return action.delete(db.connection());
+                }
+            }
+        }
+
+        let message = &action.message;
+        let result = match action.action.clone() {
+            OutgoingSanction::Reply(input_message) => {
+                info!("Sending message");
+                send_reply(message, input_message, client_id).await
+            }
+            OutgoingSanction::Report(text) => {
+                info!(
+                    "Reporting message {} of chat {}",
+                    message.id, message.chat_id
+                );
+                report(message, text, client_id).await
+            }
+        };
+        rate_limiter.record(action.chat_id);
+        settle(&db.lock().unwrap(), rate_limiter, action, result)
+    }
+
+    /// Records the outcome of the action, retry after errors keep it queued for another attempt
+    fn settle(
+        db: &Database,
+        rate_limiter: &mut RateLimiter,
+        mut action: QueuedAction,
+        result: Result<Option<i64>, Error>,
+    ) -> FetishResult<()> {
+        if let Err(e) = &result {
+            if let Some(seconds) = retry_after(e) {
+                rate_limiter.pause(seconds);
+                action.attempts += 1;
+                if action.attempts < MAX_ATTEMPTS {
+                    warn!(
+                        "Too many requests, action on message {} of chat {} retried in {seconds} seconds",
+                        action.message_id, action.chat_id
+                    );
+                    action.not_before = Utc::now().timestamp() + seconds as i64;
+                    return db.save(&action);
+                }
+            }
+            error!("Failed to send sanction: {e:#?}");
+        }
+        record_outcome(db, &action.message, result)?;
+        action.delete(db.connection())
+    }
+
+    #[derive(Debug, PartialEq)]
+    enum SendCheck {
+        Allowed,
+        /// Allowed once the slow mode delay of this supergroup expired
+        SlowMode(i64),
+        Drop(&'static str),
+    }
+
+    /// Checks the latest stored status of the chat, TDLib would refuse the reply anyway
+    fn check_send(db: &Database, chat_id: i64) -> FetishResult<SendCheck> {
+        let Some(chat) = db.load::<ChatWrapper>(chat_id)? else {
+            return Ok(SendCheck::Allowed);
+        };
+        let status = scout::member_status(db, &chat)?;
+        let slow_mode_supergroup_id = match &chat.r#type {
+            ChatType::Supergroup(supergroup) => db
+                .load::<SupergroupWrapper>(supergroup.supergroup_id)?
+                .filter(|supergroup| supergroup.is_slow_mode_enabled)
+                .map(|supergroup| supergroup.id),
+            _ => None,
+        };
+        Ok(send_check(
+            status.as_ref(),
+            &chat.permissions,
+            slow_mode_supergroup_id,
+        ))
+    }
+
+    fn send_check(
+        status: Option<&ChatMemberStatus>,
+        chat_permissions: &ChatPermissions,
+        slow_mode_supergroup_id: Option<i64>,
+    ) -> SendCheck {
+        let can_send_messages = match status {
+            // Administrators are bound by neither permissions nor slow mode
+            Some(ChatMemberStatus::Creator(_) | ChatMemberStatus::Administrator(_)) => {
+                return SendCheck::Allowed
+            }
+            Some(ChatMemberStatus::Banned(_)) => return SendCheck::Drop("banned from the chat"),
+            Some(ChatMemberStatus::Left)
+            | Some(ChatMemberStatus::Restricted(ChatMemberStatusRestricted {
+                is_member: false,
+                ..
+            })) => return SendCheck::Drop("not a member of the chat"),
+            Some(ChatMemberStatus::Restricted(restricted)) => {
+                restricted.permissions.can_send_basic_messages
+            }
+            Some(ChatMemberStatus::Member) | None => chat_permissions.can_send_basic_messages,
+        };
+        if !can_send_messages {
+            return SendCheck::Drop("not allowed to send messages");
+        }
+        slow_mode_supergroup_id.map_or(SendCheck::Allowed, SendCheck::SlowMode)
+    }
+
+    /// Returns the temporary id of the reply
+    async fn send_reply(
+        message: &Message,
+        input_message: enums::InputMessageContent,
+        client_id: i32,
+    ) -> Result<Option<i64>, Error> {
+        let enums::Message::Message(sent_message) = functions::send_message(
+            message.chat_id,
+            message.message_thread_id,
+            Some(MessageReplyTo::Message(MessageReplyToMessage {
+                chat_id: message.chat_id,
+                message_id: message.id,
+            })),
+            None,
+            input_message,
+            client_id,
+        )
+        .await?;
+        Ok(Some(sent_message.id))
+    }
+
+    /// The outcome is the one of the message report, the sender report is best effort
+    async fn report(message: &Message, text: String, client_id: i32) -> Result<Option<i64>, Error> {
+        functions::report_chat(
+            message.chat_id,
+            vec![message.id],
+            ReportReason::Spam,
+            text.clone(),
+            client_id,
+        )
+        .await?;
+        if let Err(e) = report_sender(&message.sender_id, text, client_id).await {
+            warn!(
+                "Could not report the sender of message {} of chat {}: {}: {}",
+                message.id, message.chat_id, e.code, e.message
+            );
+        }
+        Ok(None)
+    }
+
+    /// Users are reported through their private chat, channels directly
+    async fn report_sender(
+        sender: &MessageSender,
+        text: String,
+        client_id: i32,
+    ) -> Result<(), Error> {
+        let enums::Chat::Chat(chat) = match sender {
+            MessageSender::User(MessageSenderUser { user_id }) => {
+                functions::create_private_chat(*user_id, true, client_id).await?
+            }
+            MessageSender::Chat(MessageSenderChat { chat_id }) => {
+                functions::get_chat(*chat_id, client_id).await?
+            }
+        };
+        if !chat.can_be_reported {
+            debug!("Chat {} cannot be reported", chat.id);
+            return Ok(());
+        }
+        functions::report_chat(chat.id, vec![], ReportReason::Spam, text, client_id).await
+    }
+
+    fn record_skipped(db: &Database, message: &Message, reason: &str) -> FetishResult<()> {
+        let Some(mut sanction) = db.load::<Sanction>((message.chat_id, message.id))? else {
+            return Ok(());
+        };
+        sanction.status = SanctionStatus::Skipped;
+        sanction.error = Some(reason.to_owned());
+        db.save(&sanction)
+    }
+
+    fn record_outcome(
+        db: &Database,
+        message: &Message,
+        result: Result<Option<i64>, Error>,
+    ) -> FetishResult<()> {
+        let Some(mut sanction) = db.load::<Sanction>((message.chat_id, message.id))? else {
+            return Ok(());
+        };
+        match result {
+            Ok(sent_message_id) => {
+                sanction.status = SanctionStatus::Sent;
+                sanction.sent_message_id = sent_message_id;
+                sanction.sent_at = Some(Utc::now().timestamp());
             }
+            Err(e) => {
+                sanction.status = SanctionStatus::Failed;
+                sanction.error = Some(format!("{}: {}", e.code, e.message));
+            }
+        }
+        db.save(&sanction)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use tdlib::types::ChatMemberStatusAdministrator;
+
+        use super::*;
+
+        fn permissions(can_send_basic_messages: bool) -> ChatPermissions {
+            ChatPermissions {
+                can_send_basic_messages,
+                ..Default::default()
+            }
+        }
+
+        #[test]
+        fn test_muted_chat_drops_replies() {
+            assert_eq!(
+                send_check(Some(&ChatMemberStatus::Member), &permissions(false), None),
+                SendCheck::Drop("not allowed to send messages")
+            );
+            assert_eq!(
+                send_check(None, &permissions(true), None),
+                SendCheck::Allowed
+            );
+        }
+
+        #[test]
+        fn test_restricted_member_drops_replies() {
+            let restricted = ChatMemberStatus::Restricted(ChatMemberStatusRestricted {
+                is_member: true,
+                restricted_until_date: 0,
+                permissions: permissions(false),
+            });
+            assert_eq!(
+                send_check(Some(&restricted), &permissions(true), None),
+                SendCheck::Drop("not allowed to send messages")
+            );
+            let kicked = ChatMemberStatus::Restricted(ChatMemberStatusRestricted {
+                is_member: false,
+                restricted_until_date: 0,
+                permissions: permissions(true),
+            });
+            assert_eq!(
+                send_check(Some(&kicked), &permissions(true), None),
+                SendCheck::Drop("not a member of the chat")
+            );
+        }
+
+        #[test]
+        fn test_slow_mode_only_binds_members() {
+            assert_eq!(
+                send_check(Some(&ChatMemberStatus::Member), &permissions(true), Some(7)),
+                SendCheck::SlowMode(7)
+            );
+            let administrator =
+                ChatMemberStatus::Administrator(ChatMemberStatusAdministrator::default());
+            assert_eq!(
+                send_check(Some(&administrator), &permissions(false), Some(7)),
+                SendCheck::Allowed
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        detectors::{DetectionContext, ScamDetector, Verdict},
+        models::{message_wrapper::MessageWrapper, sanction::SanctionMethod},
+        test_utils,
+    };
+
+    use super::*;
+
+    /// Flags every message well over any threshold
+    struct Flag;
+
+    #[async_trait]
+    impl ScamDetector for Flag {
+        fn name(&self) -> &'static str {
+            "flag"
+        }
+
+        async fn detect(&self, _context: &DetectionContext<'_>) -> FetishResult<Option<Verdict>> {
+            Ok(Some(Verdict::new(VerdictKind::ScamMessage, 10., "flagged")))
+        }
+    }
+
+    fn state() -> ExploitationState {
+        ExploitationState::new(
+            vec![],
+            DetectorPipeline::new().add_detector(Flag),
+            test_utils::rules(),
+            CachePolicy::default(),
+        )
+    }
+
+    fn set_policy(db: &Arc<Mutex<Database>>, chat_id: i64, policy: Policy) {
+        db.lock()
+            .unwrap()
+            .save(&ChatPolicy {
+                chat_id,
+                policy,
+                updated_at: 0,
+            })
+            .unwrap();
+    }
+
+    /// Handles a message of user 42 and returns what reached the message sender
+    async fn handle(
+        state: &ExploitationState,
+        db: &Arc<Mutex<Database>>,
+        chat_id: i64,
+        message_id: i64,
+    ) -> Option<QueuedAction> {
+        let (tx, mut rx) = mpsc::channel(1);
+        let message = test_utils::message(chat_id, message_id, 42, test_utils::text("ESCORT"));
+        state
+            .handle_message(message, db.clone(), 0, 1, &tx)
+            .await
+            .unwrap();
+        rx.try_recv().ok()
+    }
+
+    fn offend(db: &Database, chat_id: i64, message_id: i64, user_id: i64) -> Message {
+        let message = test_utils::message(chat_id, message_id, user_id, test_utils::text("ESCORT"));
+        db.save(&MessageWrapper::from(message.clone())).unwrap();
+        db.save(&MessageAssessment {
+            message_id,
+            chat_id,
+            score: 2.,
+            threshold: 1.,
+            is_scam: true,
+            breakdown: "[]".into(),
+            assessed_at: Utc::now().timestamp(),
+            dry_run: false,
+        })
+        .unwrap();
+        message
+    }
+
+    #[test]
+    fn test_promotion_after_enough_offences() {
+        let db = test_utils::database();
+        let db = db.lock().unwrap();
+        let promotion = Promotion {
+            offences: 3,
+            window_seconds: 60,
+        };
+
+        for message_id in 1..3 {
+            let message = offend(&db, -100, message_id, 42);
+            promote_repeat_offender(&db, &message, &promotion).unwrap();
         }
+        assert!(db.load::<Scammer>(42).unwrap().is_none());
+
+        let message = offend(&db, -200, 3, 42);
+        promote_repeat_offender(&db, &message, &promotion).unwrap();
+        let scammer = db.load::<Scammer>(42).unwrap().unwrap();
+        assert_eq!(scammer.reason, ScammerReason::RepeatOffender);
+        assert_eq!(scammer.hit_count, 3);
+        assert_eq!(
+            ScammerEvidence::select_by_user_id(42, db.connection())
+                .unwrap()
+                .len(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ignored_and_observed_chats_get_nothing_sent() {
+        let db = test_utils::database();
+        let state = state();
+        set_policy(&db, -100, Policy::Ignore);
+        set_policy(&db, -200, Policy::Observe);
+
+        assert!(handle(&state, &db, -100, 1).await.is_none());
+        assert!(db
+            .lock()
+            .unwrap()
+            .load::<MessageAssessment>((-100, 1))
+            .unwrap()
+            .is_none());
+
+        assert!(handle(&state, &db, -200, 1).await.is_none());
+        {
+            let db = db.lock().unwrap();
+            assert!(
+                db.load::<MessageAssessment>((-200, 1))
+                    .unwrap()
+                    .unwrap()
+                    .is_scam
+            );
+            assert!(db.load::<Sanction>((-200, 1)).unwrap().is_none());
+            assert!(db.load_all::<QueuedAction>().unwrap().is_empty());
+        }
+
+        // Chats without a policy are warned
+        let action = handle(&state, &db, -300, 1).await.unwrap();
+        assert_eq!(action.action.method(), SanctionMethod::Reply);
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_records_without_queueing() {
+        let db = test_utils::database();
+        let state = state().set_dry_run(true);
+
+        assert!(handle(&state, &db, -100, 1).await.is_none());
+        let db = db.lock().unwrap();
+        assert!(
+            db.load::<MessageAssessment>((-100, 1))
+                .unwrap()
+                .unwrap()
+                .dry_run
+        );
+        let sanction = db.load::<Sanction>((-100, 1)).unwrap().unwrap();
+        assert!(sanction.is_dry_run);
+        assert_eq!(sanction.status, SanctionStatus::Skipped);
+        assert!(db.load_all::<QueuedAction>().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_report_policy_queues_a_report() {
+        let db = test_utils::database();
+        let state = state();
+        set_policy(&db, -100, Policy::Report);
+
+        let action = handle(&state, &db, -100, 1).await.unwrap();
+        let OutgoingSanction::Report(text) = &action.action else {
+            panic!("expected a report, got {:?}", action.action);
+        };
+        assert!(text.contains("flagged"));
+        let db = db.lock().unwrap();
+        let sanction = db.load::<Sanction>((-100, 1)).unwrap().unwrap();
+        assert_eq!(sanction.method, SanctionMethod::Report);
+        assert_eq!(sanction.status, SanctionStatus::Pending);
+        assert_eq!(db.load_all::<QueuedAction>().unwrap().len(), 1);
     }
 }
@@ -5,6 +5,7 @@ use async_trait::async_trait;
 pub mod closing_state;
 pub mod login_state;
 pub mod exploitation_state;
+pub mod reference_images_state;
 
 #[async_trait]
 pub trait ApplicationState: Sync + Send {
@@ -0,0 +1,75 @@
+use std::{fs, path::PathBuf};
+
+use async_trait::async_trait;
+use chrono::Utc;
+use log::{debug, info, warn};
+
+use crate::{
+    application::ApplicationData, error::FetishResult, image_hash::PerceptualHash,
+    models::image_fingerprint::ImageFingerprint,
+};
+
+use super::ApplicationState;
+
+/// Hashes the photos of models known to be stolen by scammers into the image store
+pub struct ReferenceImagesState {
+    dir: PathBuf,
+}
+
+impl ReferenceImagesState {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+}
+
+#[async_trait]
+impl ApplicationState for ReferenceImagesState {
+    async fn run(&self, app_data: ApplicationData) -> FetishResult<ApplicationData> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                warn!(
+                    "No reference images loaded from '{}': {e}",
+                    self.dir.display()
+                );
+                return Ok(app_data);
+            }
+        };
+
+        let mut fingerprints = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let hash = tokio::task::spawn_blocking({
+                let path = path.clone();
+                move || PerceptualHash::open(&path)
+            })
+            .await?;
+            match hash {
+                Ok(hash) => fingerprints.push(ImageFingerprint::reference(
+                    &path.file_name().unwrap_or_default().to_string_lossy(),
+                    hash,
+                    path.display().to_string(),
+                    Utc::now().timestamp(),
+                )),
+                Err(e) => debug!("Skipping '{}': {e:?}", path.display()),
+            }
+        }
+
+        {
+            let db = app_data.conn.lock().unwrap();
+            ImageFingerprint::delete_references(db.connection())?;
+            for fingerprint in &fingerprints {
+                db.save(fingerprint)?;
+            }
+        }
+        info!(
+            "Indexed {} reference images from '{}'",
+            fingerprints.len(),
+            self.dir.display()
+        );
+        Ok(app_data)
+    }
+}
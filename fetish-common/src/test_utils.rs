@@ -0,0 +1,103 @@
+//! Builders for the TDLib objects the tests need, TDLib types have no `Default`
+
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use tdlib::{
+    enums::{MessageContent, MessageSender, UserStatus, UserType},
+    types::{FormattedText, Message, MessageSenderUser, MessageText, User},
+};
+
+use crate::{database::Database, rules::RulesStore};
+
+pub fn database() -> Arc<Mutex<Database>> {
+    Arc::new(Mutex::new(Database::new(Path::new(":memory:")).unwrap()))
+}
+
+/// The rules shipped in `res`
+pub fn rules() -> Arc<RulesStore> {
+    Arc::new(RulesStore::load(Path::new(env!("CARGO_MANIFEST_DIR")).join("../res")).unwrap())
+}
+
+pub fn text(text: &str) -> MessageContent {
+    MessageContent::MessageText(MessageText {
+        text: FormattedText {
+            text: text.into(),
+            entities: vec![],
+        },
+        web_page: None,
+    })
+}
+
+pub fn message(chat_id: i64, id: i64, user_id: i64, content: MessageContent) -> Message {
+    Message {
+        id,
+        sender_id: MessageSender::User(MessageSenderUser { user_id }),
+        chat_id,
+        sending_state: None,
+        scheduling_state: None,
+        is_outgoing: false,
+        is_pinned: false,
+        can_be_edited: false,
+        can_be_forwarded: true,
+        can_be_saved: true,
+        can_be_deleted_only_for_self: false,
+        can_be_deleted_for_all_users: false,
+        can_get_added_reactions: false,
+        can_get_statistics: false,
+        can_get_message_thread: false,
+        can_get_viewers: false,
+        can_get_media_timestamp_links: false,
+        can_report_reactions: false,
+        has_timestamped_media: false,
+        is_channel_post: false,
+        is_topic_message: false,
+        contains_unread_mention: false,
+        date: 0,
+        edit_date: 0,
+        forward_info: None,
+        interaction_info: None,
+        unread_reactions: vec![],
+        reply_to: None,
+        message_thread_id: 0,
+        self_destruct_type: None,
+        self_destruct_in: 0.,
+        auto_delete_in: 0.,
+        via_bot_user_id: 0,
+        author_signature: String::new(),
+        media_album_id: 0,
+        restriction_reason: String::new(),
+        content,
+        reply_markup: None,
+    }
+}
+
+pub fn user(id: i64, first_name: &str) -> User {
+    User {
+        id,
+        first_name: first_name.into(),
+        last_name: String::new(),
+        usernames: None,
+        phone_number: String::new(),
+        status: UserStatus::Empty,
+        profile_photo: None,
+        emoji_status: None,
+        is_contact: false,
+        is_mutual_contact: false,
+        is_close_friend: false,
+        is_verified: false,
+        is_premium: false,
+        is_support: false,
+        restriction_reason: String::new(),
+        is_scam: false,
+        is_fake: false,
+        has_active_stories: false,
+        has_unread_active_stories: false,
+        have_access: true,
+        r#type: UserType::Regular,
+        language_code: String::new(),
+        added_to_attachment_menu: false,
+    }
+}
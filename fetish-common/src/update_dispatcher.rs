@@ -1,15 +1,16 @@
 use std::{
+    ops::Deref,
     pin::Pin,
     sync::{Arc, Mutex},
     task::{Context, Poll},
 };
 
+use chrono::Utc;
 use futures::{Stream, StreamExt};
-use log::{error, info, trace};
+use log::{error, info, trace, warn};
 use tdlib::{
-    enums::{AuthorizationState, MessageContent, Update},
-    functions,
-    types::{Message, PhotoSize},
+    enums::{AuthorizationState, ChatMemberStatus, Update},
+    types::{File, Message},
 };
 use tokio::sync::{
     broadcast,
@@ -18,14 +19,21 @@ use tokio::sync::{
 
 use crate::{
     database::Database,
+    detectors::profile_detector::ProfileDetector,
     error::FetishResult,
+    image_hash,
+    media::{self, DownloadPolicy},
     models::{
-        basic_group_wrapper::BasicGroupWrapper, chat_wrapper::ChatWrapper,
-        message_wrapper::MessageWrapper, supergroup_wrapper::SupergroupWrapper,
+        basic_group_wrapper::BasicGroupWrapper, chat_policy::ChatPolicy, chat_wrapper::ChatWrapper,
+        membership_change::MembershipChange, message_wrapper::MessageWrapper, sanction::Sanction,
+        scouted_chat::ScoutedChat, supergroup_wrapper::SupergroupWrapper,
         user_wrapper::UserWrapper,
     },
 };
 
+/// Chat ids of supergroups are their id subtracted from it
+const SUPERGROUP_CHAT_ID_OFFSET: i64 = -1_000_000_000_000;
+
 struct UpdateStream;
 
 impl Stream for UpdateStream {
@@ -62,6 +70,8 @@ pub struct UpdateDispatcher {
     auth_tx: mpsc::UnboundedSender<AuthorizationState>,
     message_tx: mpsc::UnboundedSender<Message>,
     db: Arc<Mutex<Database>>,
+    profile_detector: Option<ProfileDetector>,
+    download_policy: DownloadPolicy,
 }
 
 impl UpdateDispatcher {
@@ -70,12 +80,16 @@ impl UpdateDispatcher {
         auth_tx: mpsc::UnboundedSender<AuthorizationState>,
         message_tx: mpsc::UnboundedSender<Message>,
         db: Arc<Mutex<Database>>,
+        profile_detector: Option<ProfileDetector>,
+        download_policy: DownloadPolicy,
     ) -> FetishResult<Self> {
         Ok(Self {
             shutdown_rx,
             auth_tx,
             message_tx,
             db,
+            profile_detector,
+            download_policy,
         })
     }
 
@@ -104,52 +118,103 @@ impl UpdateDispatcher {
             Update::AuthorizationState(update) => {
                 Ok(self.auth_tx.send(update.authorization_state)?)
             }
-            Update::NewMessage(message) => {
-                if let MessageContent::MessagePhoto(message_photo) = &message.message.content {
-                    if let Some(photo) = message_photo.photo.sizes.iter().fold(
-                        None,
-                        |acc: Option<&PhotoSize>, photo| {
-                            if let Some(acc) = acc {
-                                if photo.width * photo.height > acc.width * acc.height {
-                                    Some(photo)
-                                } else {
-                                    Some(acc)
-                                }
-                            } else {
-                                Some(photo)
-                            }
-                        },
-                    ) {
-                        download_file(photo.photo.id, client_id);
-                    }
-                } else if let MessageContent::MessageVideo(message_video) = &message.message.content
-                {
-                    download_file(message_video.video.video.id, client_id);
-                } else if let MessageContent::MessageAnimation(message_animation) =
-                    &message.message.content
-                {
-                    download_file(message_animation.animation.animation.id, client_id);
-                }
-                if let Err(e) = self
+            Update::NewMessage(message) => self.handle_message(message.message, client_id),
+            Update::MessageContent(tdlib::types::UpdateMessageContent {
+                chat_id,
+                message_id,
+                new_content,
+            }) => {
+                let stored = match self
                     .db
                     .lock()
                     .unwrap()
-                    .save(&MessageWrapper::from(message.message.clone()))
+                    .load::<MessageWrapper>((chat_id, message_id))
                 {
-                    error!("{e:#?}");
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        error!("{e:#?}");
+                        return Ok(());
+                    }
+                };
+                let Some(stored) = stored else {
+                    trace!("Edited message {message_id} of chat {chat_id} was never received");
+                    return Ok(());
+                };
+                // Scammers post something innocent first and edit the bait in afterwards
+                let mut message = stored.deref().clone();
+                message.content = new_content;
+                self.handle_message(message, client_id)
+            }
+            Update::MessageEdited(tdlib::types::UpdateMessageEdited {
+                chat_id,
+                message_id,
+                edit_date,
+                ..
+            }) => {
+                let db = self.db.lock().unwrap();
+                let stored = match db.load::<MessageWrapper>((chat_id, message_id)) {
+                    Ok(stored) => stored,
+                    Err(e) => {
+                        error!("{e:#?}");
+                        return Ok(());
+                    }
+                };
+                if let Some(stored) = stored {
+                    let mut message = stored.deref().clone();
+                    message.edit_date = edit_date;
+                    if let Err(e) = db.save(&MessageWrapper::from(message)) {
+                        error!("{e:#?}");
+                    }
                 }
-                Ok(self.message_tx.send(message.message)?)
+                Ok(())
             }
             Update::NewChat(tdlib::types::UpdateNewChat { chat }) => {
-                if let Some(photo) = &chat.photo {
-                    download_file(photo.big.id, client_id);
+                if let Some(file) = chat.photo.as_ref().and_then(|photo| {
+                    self.download_policy.profile_photo_to_hash(
+                        self.is_monitored(chat.id),
+                        &photo.small,
+                        &photo.big,
+                    )
+                }) {
+                    self.index_image(file.clone(), chat.id, 0, client_id);
                 }
                 if let Err(e) = self.db.lock().unwrap().save(&ChatWrapper::from(chat)) {
                     error!("{e:#?}");
                 }
                 Ok(())
             }
+            Update::ChatPermissions(tdlib::types::UpdateChatPermissions {
+                chat_id,
+                permissions,
+            }) => {
+                // The message sender checks them before replying
+                let db = self.db.lock().unwrap();
+                match db.load::<ChatWrapper>(chat_id) {
+                    Ok(Some(stored)) => {
+                        let mut chat = stored.deref().clone();
+                        chat.permissions = permissions;
+                        if let Err(e) = db.save(&ChatWrapper::from(chat)) {
+                            error!("{e:#?}");
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => error!("{e:#?}"),
+                }
+                Ok(())
+            }
             Update::Supergroup(tdlib::types::UpdateSupergroup { supergroup }) => {
+                if let Err(e) = self.track_membership(
+                    SUPERGROUP_CHAT_ID_OFFSET - supergroup.id,
+                    |db| {
+                        Ok(db
+                            .load::<SupergroupWrapper>(supergroup.id)?
+                            .map(|previous| previous.status.clone()))
+                    },
+                    &supergroup.status,
+                    "supergroup",
+                ) {
+                    error!("{e:#?}");
+                }
                 if let Err(e) = self
                     .db
                     .lock()
@@ -161,6 +226,18 @@ impl UpdateDispatcher {
                 Ok(())
             }
             Update::BasicGroup(tdlib::types::UpdateBasicGroup { basic_group }) => {
+                if let Err(e) = self.track_membership(
+                    -basic_group.id,
+                    |db| {
+                        Ok(db
+                            .load::<BasicGroupWrapper>(basic_group.id)?
+                            .map(|previous| previous.status.clone()))
+                    },
+                    &basic_group.status,
+                    "basic_group",
+                ) {
+                    error!("{e:#?}");
+                }
                 if let Err(e) = self
                     .db
                     .lock()
@@ -172,25 +249,232 @@ impl UpdateDispatcher {
                 Ok(())
             }
             Update::User(tdlib::types::UpdateUser { user }) => {
-                if let Some(photo) = &user.profile_photo {
-                    download_file(photo.big.id, client_id);
+                if let Some(file) = user.profile_photo.as_ref().and_then(|photo| {
+                    self.download_policy
+                        .profile_photo_to_hash(true, &photo.small, &photo.big)
+                }) {
+                    self.index_image(file.clone(), 0, user.id, client_id);
+                }
+                if let Some(profile_detector) = &self.profile_detector {
+                    let profile = profile_detector.assess(&user);
+                    if profile.score > 0. {
+                        info!(
+                            "User {} profile scored {}: {}",
+                            user.id, profile.score, profile.reason
+                        );
+                    }
+                    if let Err(e) = self.db.lock().unwrap().save(&profile) {
+                        error!("{e:#?}");
+                    }
                 }
                 if let Err(e) = self.db.lock().unwrap().save(&UserWrapper::from(user)) {
                     error!("{e:#?}");
                 }
                 Ok(())
             }
+            Update::MessageSendSucceeded(tdlib::types::UpdateMessageSendSucceeded {
+                message,
+                old_message_id,
+            }) => {
+                if let Err(e) = Sanction::confirm_sent(
+                    message.chat_id,
+                    old_message_id,
+                    message.id,
+                    self.db.lock().unwrap().connection(),
+                ) {
+                    error!("{e:#?}");
+                }
+                Ok(())
+            }
+            Update::MessageSendFailed(tdlib::types::UpdateMessageSendFailed {
+                message,
+                old_message_id,
+                error,
+            }) => {
+                if let Err(e) = Sanction::mark_send_failed(
+                    message.chat_id,
+                    old_message_id,
+                    &format!("{}: {}", error.code, error.message),
+                    self.db.lock().unwrap().connection(),
+                ) {
+                    error!("{e:#?}");
+                }
+                Ok(())
+            }
             _ => Ok(()),
         }
     }
-}
 
-fn download_file(file_id: i32, client_id: i32) {
-    tokio::spawn(async move {
-        if let Err(e) = functions::download_file(file_id, 1, 0, 0, true, client_id).await {
+    /// New and edited messages are stored then assessed
+    fn handle_message(&self, message: Message, client_id: i32) -> Result<(), UpdateDispatchError> {
+        if let Some(file) = self
+            .download_policy
+            .file_to_download(self.is_monitored(message.chat_id), &message.content)
+        {
+            self.download_file(file.clone(), client_id);
+        }
+        if let Err(e) = self
+            .db
+            .lock()
+            .unwrap()
+            .save(&MessageWrapper::from(message.clone()))
+        {
             error!("{e:#?}");
-        } else {
-            trace!("Downloaded file: {file_id}");
         }
-    });
+        Ok(self.message_tx.send(message)?)
+    }
+
+    /// Records changes of our own status against the stored one, chats where we are banned, kicked
+    /// or muted are marked as lost for the scout
+    ///
+    /// `updateChatMember` is only sent to bots, group updates carry our status for user accounts.
+    fn track_membership(
+        &self,
+        chat_id: i64,
+        previous_status: impl FnOnce(&Database) -> FetishResult<Option<ChatMemberStatus>>,
+        status: &ChatMemberStatus,
+        source: &str,
+    ) -> FetishResult<()> {
+        let db = self.db.lock().unwrap();
+        let Some(old_status) = previous_status(&db)? else {
+            return Ok(());
+        };
+        if &old_status == status {
+            return Ok(());
+        }
+
+        let now = Utc::now().timestamp();
+        let (was_lost, is_lost) = (
+            MembershipChange::is_lost(&old_status),
+            MembershipChange::is_lost(status),
+        );
+        if is_lost && !was_lost {
+            warn!("Lost chat {chat_id}: {status:?}");
+            ScoutedChat::set_lost_at(chat_id, Some(now), db.connection())?;
+        } else if was_lost && !is_lost {
+            info!("Back in chat {chat_id}: {status:?}");
+            ScoutedChat::set_lost_at(chat_id, None, db.connection())?;
+        }
+        db.save(&MembershipChange {
+            id: None,
+            chat_id,
+            changed_at: now,
+            old_status,
+            new_status: status.clone(),
+            source: source.to_owned(),
+        })
+    }
+
+    /// Unreadable policies count as unmonitored, nothing gets downloaded for them
+    fn is_monitored(&self, chat_id: i64) -> bool {
+        ChatPolicy::is_monitored(chat_id, self.db.lock().unwrap().connection()).unwrap_or_else(
+            |e| {
+                error!("{e:#?}");
+                false
+            },
+        )
+    }
+
+    fn download_file(&self, file: File, client_id: i32) {
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = media::download(db, &file, client_id).await {
+                error!("{e:#?}");
+            } else {
+                trace!("Downloaded file: {}", file.id);
+            }
+        });
+    }
+
+    /// Profile photos allowed by the download policy are hashed as they come, message photos when
+    /// they get assessed
+    fn index_image(&self, file: File, chat_id: i64, user_id: i64, client_id: i32) {
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            if let Err(e) = image_hash::index_image(db, &file, chat_id, 0, user_id, client_id).await
+            {
+                error!("{e:#?}");
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tdlib::types::UpdateMessageContent;
+
+    use crate::test_utils;
+
+    use super::*;
+
+    #[test]
+    fn test_edited_content_is_assessed_in_its_own_chat() {
+        let db = test_utils::database();
+        let (_shutdown_tx, shutdown_rx) = broadcast::channel(1);
+        let (auth_tx, _auth_rx) = mpsc::unbounded_channel();
+        let (message_tx, mut message_rx) = mpsc::unbounded_channel();
+        let dispatcher = UpdateDispatcher::new(
+            shutdown_rx,
+            auth_tx,
+            message_tx,
+            db.clone(),
+            None,
+            DownloadPolicy::default(),
+        )
+        .unwrap();
+        for chat_id in [-100, -200] {
+            db.lock()
+                .unwrap()
+                .save(&MessageWrapper::from(test_utils::message(
+                    chat_id,
+                    1,
+                    42,
+                    test_utils::text("hello"),
+                )))
+                .unwrap();
+        }
+
+        dispatcher
+            .handle_update(
+                Update::MessageContent(UpdateMessageContent {
+                    chat_id: -200,
+                    message_id: 1,
+                    new_content: test_utils::text("ESCORT"),
+                }),
+                0,
+            )
+            .unwrap();
+        let message = message_rx.try_recv().unwrap();
+        assert_eq!(message.chat_id, -200);
+        assert_eq!(message.content, test_utils::text("ESCORT"));
+        let db = db.lock().unwrap();
+        assert_eq!(
+            db.load::<MessageWrapper>((-100, 1))
+                .unwrap()
+                .unwrap()
+                .content,
+            test_utils::text("hello")
+        );
+        assert_eq!(
+            db.load::<MessageWrapper>((-200, 1))
+                .unwrap()
+                .unwrap()
+                .content,
+            test_utils::text("ESCORT")
+        );
+
+        // Messages received before the bot started can't be told apart
+        drop(db);
+        dispatcher
+            .handle_update(
+                Update::MessageContent(UpdateMessageContent {
+                    chat_id: -300,
+                    message_id: 1,
+                    new_content: test_utils::text("ESCORT"),
+                }),
+                0,
+            )
+            .unwrap();
+        assert!(message_rx.try_recv().is_err());
+    }
 }
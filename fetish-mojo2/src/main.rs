@@ -117,7 +117,10 @@ WHERE first_name LIKE :first_name
                         if *is_scammer {
                             "DELETE FROM SCAMMERS WHERE user_id = :user_id"
                         } else {
-                            "INSERT INTO SCAMMERS (user_id) VALUES (:user_id)"
+                            r"
+INSERT INTO SCAMMERS (user_id, reason, added_by, first_seen, last_seen)
+VALUES (:user_id, 'manual_flag', 'mojo2', strftime('%s', 'now'), strftime('%s', 'now'))
+"
                         },
                         rusqlite::named_params! {
                             ":user_id": user_id,